@@ -11,6 +11,7 @@ pub trait Day {
     fn part_2_name(&self) -> &'static str;
     fn run_part_1(&self, input: &str) -> PartOutput;
     fn run_part_2(&self, input: &str) -> PartOutput;
+    fn exports(&self) -> &'static [Export];
 }
 
 #[derive(Debug)]
@@ -39,6 +40,17 @@ pub struct DaySpec<T: Display, U: Display> {
     pub part_2_name: &'static str,
     pub part_1: fn(&str) -> T,
     pub part_2: fn(&str) -> U,
+    pub exports: &'static [Export],
+}
+
+#[derive(Copy, Clone)]
+pub struct Export {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub run: fn(&str, &[String]) -> String,
+    /// Whether the export looks at the day's input, which is only read when it does, so that
+    /// exports that don't can be run without it.
+    pub needs_input: bool,
 }
 
 impl<T: Display, U: Display> Day for DaySpec<T, U> {
@@ -75,6 +87,10 @@ impl<T: Display, U: Display> Day for DaySpec<T, U> {
             (self.part_2)(input).to_string()
         )
     }
+
+    fn exports(&self) -> &'static [Export] {
+        self.exports
+    }
 }
 
 pub struct DaySpecTodo {
@@ -105,6 +121,10 @@ impl Day for DaySpecTodo {
     fn run_part_2(&self, _input: &str) -> PartOutput {
         PartOutput::Todo
     }
+
+    fn exports(&self) -> &'static [Export] {
+        &[]
+    }
 }
 
 #[derive(Eq, PartialEq, Hash, Copy, Clone)]
@@ -7,6 +7,7 @@ pub const DAY_ONE: DaySpec<u32, u32> = DaySpec {
     part_1,
     part_2_name: "similarity score",
     part_2,
+    exports: &[],
 };
 
 fn part_1(input: &str) -> u32 {
@@ -7,6 +7,7 @@ pub const DAY_TWO: DaySpec<usize, usize> = DaySpec {
     part_1,
     part_2_name: "actual safe reports",
     part_2,
+    exports: &[],
 };
 
 fn part_1(input: &str) -> usize {
@@ -8,6 +8,7 @@ pub const DAY_THREE: DaySpec<u32, u32> = DaySpec {
     part_1,
     part_2_name: "enabled multiplication sum",
     part_2,
+    exports: &[],
 };
 const PATTERN: &str = "mul\\(([1-9][0-9]*),([1-9][0-9]*)\\)";
 
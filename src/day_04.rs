@@ -6,6 +6,7 @@ pub const DAY_FOUR: DaySpec<u32, u32> = DaySpec {
     part_1,
     part_2_name: "X-MAS matches",
     part_2,
+    exports: &[],
 };
 
 fn part_1(input: &str) -> u32 {
@@ -1,6 +1,7 @@
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
-use crate::common::DaySpec;
+use crate::common::{DaySpec, Export};
+use crate::dot::DotGraph;
 
 pub const DAY_FIVE: DaySpec<u32, u32> = DaySpec {
     day_num: 5,
@@ -8,6 +9,14 @@ pub const DAY_FIVE: DaySpec<u32, u32> = DaySpec {
     part_1,
     part_2_name: "middle page sum of incorrectly-ordered updates, after ordering",
     part_2,
+    exports: &[
+        Export {
+            name: "dot",
+            description: "page ordering rules as a Graphviz graph, with violated rules highlighted",
+            run: export_dot,
+            needs_input: true,
+        },
+    ],
 };
 
 fn part_1(input: &str) -> u32 {
@@ -58,6 +67,58 @@ fn is_ordered(page_ordering_rules: &HashMap<u32, HashSet<u32>>, update: &[u32])
     true
 }
 
+fn violated_rules(
+    page_ordering_rules: &HashMap<u32, HashSet<u32>>,
+    update: &[u32],
+) -> Vec<(u32, u32)> {
+    let mut violated = Vec::new();
+    for (i, &page) in update.iter().enumerate() {
+        if let Some(prev_pages) = page_ordering_rules.get(&page) {
+            for &later_page in &update[i + 1..] {
+                if prev_pages.contains(&later_page) {
+                    violated.push((later_page, page));
+                }
+            }
+        }
+    }
+    violated
+}
+
+fn export_dot(input: &str, _args: &[String]) -> String {
+    let (page_ordering_rules, updates) = parse_input(input);
+    let mut violation_counts: HashMap<(u32, u32), usize> = HashMap::new();
+    for update in &updates {
+        for rule in violated_rules(&page_ordering_rules, update) {
+            *violation_counts.entry(rule).or_insert(0) += 1;
+        }
+    }
+    let mut rules: Vec<(u32, u32)> = page_ordering_rules.iter()
+        .flat_map(|(&second, firsts)| firsts.iter().map(move |&first| (first, second)))
+        .collect();
+    rules.sort_unstable();
+    let mut pages: Vec<u32> = rules.iter()
+        .flat_map(|&(first, second)| [first, second])
+        .collect();
+    pages.sort_unstable();
+    pages.dedup();
+
+    let mut graph = DotGraph::new("page_ordering_rules");
+    for page in pages {
+        graph.add_node(&page.to_string(), &[]);
+    }
+    for (first, second) in rules {
+        match violation_counts.get(&(first, second)) {
+            Some(count) => graph.add_edge(&first.to_string(), &second.to_string(), &[
+                ("color", "red"),
+                ("penwidth", "2"),
+                ("tooltip", &format!("violated by {} update(s)", count)),
+            ]),
+            None => graph.add_edge(&first.to_string(), &second.to_string(), &[]),
+        }
+    }
+    graph.to_string()
+}
+
 fn middle_page(update: &[u32]) -> u32 {
     update[update.len() / 2]
 }
@@ -125,4 +186,15 @@ mod tests {
     fn part_2_sample() {
         assert_eq!(123, part_2(INPUT));
     }
+
+    #[test]
+    fn export_dot_sample() {
+        let dot = export_dot(INPUT, &[]);
+        assert!(dot.starts_with("digraph \"page_ordering_rules\" {"));
+        assert_eq!(21, dot.matches(" -> ").count());
+        // 75,97,47,61,53 breaks 97|75; 61,13,29 and 97,13,75,29,47 both break 29|13, and the
+        // latter also breaks 75|13, 47|13 and 47|29
+        assert_eq!(5, dot.matches("color=\"red\"").count());
+        assert!(dot.contains("\"97\" -> \"75\" [color=\"red\""));
+    }
 }
\ No newline at end of file
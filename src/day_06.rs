@@ -1,4 +1,7 @@
-use crate::common::{DaySpec, Dimensions, Direction, Point};
+mod patrol;
+
+use crate::common::{DaySpec, Dimensions, Direction, Export, Point};
+use crate::day_06::patrol::{DirectedPosition, Patrol, Route};
 use std::collections::HashSet;
 
 pub const DAY_SIX: DaySpec<usize, usize> = DaySpec {
@@ -7,122 +10,141 @@ pub const DAY_SIX: DaySpec<usize, usize> = DaySpec {
     part_1,
     part_2_name: "possible positions for obstruction",
     part_2,
+    exports: &[
+        Export {
+            name: "loops",
+            description: "obstruction positions that trap a guard, with the loop each one causes; \
+                pass x,y to draw the route for one of them",
+            run: export_loops,
+            needs_input: true,
+        },
+    ],
 };
 
 fn part_1(input: &str) -> usize {
-    let (obstacles, Dimensions { width, length }, mut current) = parse_input(input);
-    let mut visited = HashSet::new();
-    while in_map(current.pos, width, length) {
-        visited.insert(current.pos);
-        current = step_guard(&current, &obstacles, None);
-    }
-    visited.len()
+    let (patrol, guards) = parse_input(input);
+    patrol.visited(&guards).len()
 }
 
 fn part_2(input: &str) -> usize {
-    let (obstacles, dimensions, start) = parse_input(input);
-    let route = calculate_route(&obstacles, dimensions, &start);
-
-    let mut route_traversed = HashSet::new();
-    let mut tiles_visited = HashSet::new();
-    let mut infinite_loop_positions = HashSet::new();
-
-    for current in route.iter().take(route.len() - 1) {
-        route_traversed.insert(current);
-        tiles_visited.insert(current.pos);
-
-        let new_obstacle = current.pos.step(current.dir);
-        if obstacles.contains(&new_obstacle) || tiles_visited.contains(&new_obstacle) {
-            continue;
-        }
-
-        let mut diverted_current = current.clone();
-        let mut diverted_route_traversed = HashSet::new();
-
-        while diverted_current.pos.in_bounds(dimensions.width, dimensions.length) {
-            diverted_current = step_guard(&diverted_current, &obstacles, Some(new_obstacle));
-
-            if route_traversed.contains(&diverted_current) || diverted_route_traversed.contains(&diverted_current) {
-                infinite_loop_positions.insert(new_obstacle);
-                break;
-            }
-
-            diverted_route_traversed.insert(diverted_current.clone());
-        }
-    }
-    infinite_loop_positions.len()
+    let (mut patrol, guards) = parse_input(input);
+    loop_obstacle_positions(&mut patrol, &guards).len()
 }
 
-fn calculate_route(
-    obstacles: &HashSet<Point>,
-    dimensions: Dimensions,
-    start: &DirectedPosition
-) -> Vec<DirectedPosition> {
-    let mut current = start.clone();
-    let mut route = Vec::new();
-    while in_map(current.pos, dimensions.width, dimensions.length) {
-        route.push(current.clone());
-        current = step_guard(&current, obstacles, None);
-    }
-    route
+fn loop_obstacle_positions(patrol: &mut Patrol, guards: &[DirectedPosition]) -> HashSet<Point> {
+    // an obstacle can only change a guard's route if it's somewhere on that route, and it
+    // can't be placed where a guard is already standing
+    let guard_positions: HashSet<Point> = guards.iter()
+        .map(|guard| guard.pos)
+        .collect();
+    let mut candidates: Vec<Point> = patrol.visited(guards).into_iter()
+        .filter(|pos| !guard_positions.contains(pos))
+        .collect();
+    candidates.retain(|&candidate| {
+        patrol.insert_obstacle(candidate);
+        let loops = patrol.loops(guards);
+        patrol.remove_obstacle(candidate);
+        loops
+    });
+    candidates.into_iter().collect()
 }
 
-fn in_map(point: Point, width: usize, length: usize) -> bool {
-    point.x >= 0 && point.x < isize::try_from(length).unwrap() &&
-        point.y >= 0 && point.y < isize::try_from(width).unwrap()
+/// For each obstruction position that traps at least one guard, the route of every guard it
+/// traps, in reading order of the obstruction positions.
+fn loop_reports(
+    patrol: &mut Patrol,
+    guards: &[DirectedPosition],
+) -> Vec<(Point, Vec<(usize, Route)>)> {
+    let mut obstacles: Vec<Point> = loop_obstacle_positions(patrol, guards).into_iter().collect();
+    obstacles.sort_unstable_by_key(|obstacle| (obstacle.y, obstacle.x));
+    obstacles.into_iter()
+        .map(|obstacle| {
+            patrol.insert_obstacle(obstacle);
+            let routes = patrol.routes(guards).into_iter()
+                .enumerate()
+                .filter(|(_, route)| route.loop_entry.is_some())
+                .collect();
+            patrol.remove_obstacle(obstacle);
+            (obstacle, routes)
+        })
+        .collect()
 }
 
-fn step_guard(
-    current: &DirectedPosition,
-    obstacles: &HashSet<Point>,
-    extra_obstacle: Option<Point>) -> DirectedPosition {
-    let next_pos = current.pos.step(current.dir);
-    if obstacles.contains(&next_pos) || extra_obstacle.map_or(false, |o| o == next_pos)
-    {
-        DirectedPosition { pos: current.pos, dir: current.dir.rotate_clockwise() }
-    } else {
-        DirectedPosition { pos: next_pos, dir: current.dir }
+fn export_loops(input: &str, args: &[String]) -> String {
+    let (mut patrol, guards) = parse_input(input);
+    let reports = loop_reports(&mut patrol, &guards);
+    if let Some(obstacle_arg) = args.first() {
+        let (x, y) = obstacle_arg.split_once(',')
+            .expect("obstruction position should be given as x,y");
+        let obstacle = Point { x: x.parse().unwrap(), y: y.parse().unwrap() };
+        let Some((_, routes)) = reports.iter().find(|(pos, _)| *pos == obstacle) else {
+            return format!("an obstruction at {},{} doesn't trap any guard\n", x, y);
+        };
+        patrol.insert_obstacle(obstacle);
+        let route: Vec<DirectedPosition> = routes.iter()
+            .flat_map(|(_, route)| route.steps.iter().cloned())
+            .collect();
+        let map = patrol.render(&guards, &route, Some(obstacle));
+        patrol.remove_obstacle(obstacle);
+        return map;
+    }
+    let mut output = String::new();
+    for (obstacle, routes) in reports {
+        for (guard_num, route) in routes {
+            let cycle = route.cycle().unwrap();
+            output.push_str(&format!(
+                "{},{}: guard {} enters a loop of {} steps at step {}, starting at {},{} facing {}\n",
+                obstacle.x,
+                obstacle.y,
+                guard_num,
+                cycle.len(),
+                route.loop_entry.unwrap(),
+                cycle[0].pos.x,
+                cycle[0].pos.y,
+                cycle[0].dir.tile(),
+            ));
+        }
     }
+    output
 }
 
-fn parse_input(input: &str) -> (HashSet<Point>, Dimensions, DirectedPosition) {
+fn parse_input(input: &str) -> (Patrol, Vec<DirectedPosition>) {
     let mut width = 0;
     let mut length = 0;
     let mut obstacles = HashSet::new();
-    let mut start: Option<DirectedPosition> = None;
+    let mut guards = Vec::new();
     for (row, line) in input.lines().enumerate() {
         if row == 0 {
             width = line.len();
         }
         for (col, tile) in line.chars().enumerate() {
+            let pos = Point {
+                x: isize::try_from(col).unwrap(),
+                y: isize::try_from(row).unwrap()
+            };
             if tile == '#' {
-                obstacles.insert(Point {
-                    x: isize::try_from(col).unwrap(),
-                    y: isize::try_from(row).unwrap()
-                });
+                obstacles.insert(pos);
             } else if let Some(dir) = Direction::from_tile(tile) {
-                if start.replace(
-                    DirectedPosition { pos: Point {
-                        x: isize::try_from(col).unwrap(),
-                        y: isize::try_from(row).unwrap()
-                    }, dir }
-                ).is_some() {
-                    panic!("multiple guards found");
-                }
+                guards.push(DirectedPosition { pos, dir });
             }
         }
         length += 1;
     }
-    (obstacles, Dimensions { width, length }, start.unwrap())
+    (Patrol::new(Dimensions { width, length }, &obstacles), guards)
 }
 
 impl Point {
     fn step(&self, dir: Direction) -> Point {
+        self.step_by(dir, 1)
+    }
+
+    fn step_by(&self, dir: Direction, count: usize) -> Point {
+        let count = isize::try_from(count).unwrap();
         match dir {
-            Direction::North => Point { x: self.x, y: self.y - 1 },
-            Direction::East => Point { x: self.x + 1, y: self.y },
-            Direction::South => Point { x: self.x, y: self.y + 1 },
-            Direction::West => Point { x: self.x - 1, y: self.y },
+            Direction::North => Point { x: self.x, y: self.y - count },
+            Direction::East => Point { x: self.x + count, y: self.y },
+            Direction::South => Point { x: self.x, y: self.y + count },
+            Direction::West => Point { x: self.x - count, y: self.y },
         }
     }
 }
@@ -137,10 +159,16 @@ impl Direction {
             _ => None,
         }
     }
-}
 
-#[derive(Eq, PartialEq, Hash, Clone)]
-struct DirectedPosition { pos: Point, dir: Direction }
+    fn tile(&self) -> char {
+        match self {
+            Direction::North => '^',
+            Direction::East => '>',
+            Direction::South => 'v',
+            Direction::West => '<',
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -166,4 +194,81 @@ mod tests {
     fn part_2_sample() {
         assert_eq!(6, part_2(INPUT));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn multiple_guards() {
+        let input = "....#.....
+.........#
+..........
+..#.......
+.......#..
+..........
+.#..^.....
+........#.
+#.........
+......#.<.";
+        // the second guard starts at (8,9) facing west, turns north at the # at (6,9) and east at
+        // the one at (7,4), and leaves by the east edge from (9,5); of the cells on that route,
+        // only (8,9), (7,5) and (9,5) aren't on the first guard's route too
+        assert_eq!(41 + 3, part_1(input));
+        let (mut patrol, guards) = parse_input(input);
+        assert_eq!(2, guards.len());
+        assert!(loop_obstacle_positions(&mut patrol, &guards).contains(&Point { x: 3, y: 6 }));
+    }
+
+    #[test]
+    fn guards_block_each_other() {
+        // the first guard would walk east through the second guard's cell and off the map, but
+        // the second guard is in the way when it starts, so it turns and follows it south instead
+        let input = ">v
+..
+..";
+        assert_eq!(6, part_1(input));
+        let (patrol, guards) = parse_input(input);
+        let routes = patrol.routes(&guards);
+        let positions: Vec<(isize, isize)> = routes[0].steps.iter()
+            .map(|step| (step.pos.x, step.pos.y))
+            .collect();
+        assert_eq!(vec![(0, 0), (0, 0), (0, 1), (0, 2)], positions);
+
+        // once the other guard has left the map, it no longer gets in the way
+        assert_eq!(3, part_1(">.^"));
+    }
+
+    #[test]
+    fn loop_reports_sample() {
+        let (mut patrol, guards) = parse_input(INPUT);
+        let reports = loop_reports(&mut patrol, &guards);
+        let obstacles: Vec<(isize, isize)> = reports.iter()
+            .map(|(obstacle, _)| (obstacle.x, obstacle.y))
+            .collect();
+        assert_eq!(vec![(3, 6), (6, 7), (7, 7), (1, 8), (3, 8), (7, 9)], obstacles);
+
+        // with the obstruction next to the guard's start, it turns straight back into its own path
+        let (_, routes) = &reports[0];
+        let route = &routes[0].1;
+        assert_eq!(0, route.loop_entry.unwrap());
+        assert_eq!(route.steps.len(), route.cycle().unwrap().len());
+        assert!(route.steps[0] == DirectedPosition { pos: Point { x: 4, y: 6 }, dir: Direction::North });
+
+        // elsewhere the guard walks part of its original route before getting stuck
+        let (_, routes) = &reports[5];
+        assert!(routes[0].1.loop_entry.unwrap() > 0);
+    }
+
+    #[test]
+    fn export_loops_map() {
+        let map = export_loops(INPUT, &[String::from("3,6")]);
+        assert_eq!("....#.....
+....+---+#
+....|...|.
+..#.|...|.
+....|..#|.
+....|...|.
+.#.O^---+.
+........#.
+#.........
+......#...
+", map);
+    }
+}
@@ -0,0 +1,321 @@
+use crate::common::{Dimensions, Direction, Point};
+use std::collections::{HashMap, HashSet};
+
+/// Obstacle map for one or more guards, with jump tables giving, for every cell and direction,
+/// the cell a guard walking from there stops at before the next obstacle (or `None` if it walks
+/// off the map). The guards patrol at the same time, and get in each other's way.
+pub struct Patrol {
+    dimensions: Dimensions,
+    obstacles: Vec<bool>,
+    jumps: [Vec<Option<Point>>; 4],
+}
+
+impl Patrol {
+    pub fn new(dimensions: Dimensions, obstacles: &HashSet<Point>) -> Self {
+        let cells = dimensions.width * dimensions.length;
+        let mut patrol = Patrol {
+            dimensions,
+            obstacles: vec![false; cells],
+            jumps: [vec![None; cells], vec![None; cells], vec![None; cells], vec![None; cells]],
+        };
+        for &obstacle in obstacles {
+            let index = patrol.index(obstacle);
+            patrol.obstacles[index] = true;
+        }
+        for row in 0..dimensions.length {
+            patrol.update_row_jumps(row);
+        }
+        for col in 0..dimensions.width {
+            patrol.update_col_jumps(col);
+        }
+        patrol
+    }
+
+    pub fn insert_obstacle(&mut self, point: Point) {
+        self.set_obstacle(point, true);
+    }
+
+    pub fn remove_obstacle(&mut self, point: Point) {
+        self.set_obstacle(point, false);
+    }
+
+    fn set_obstacle(&mut self, point: Point, obstacle: bool) {
+        let index = self.index(point);
+        self.obstacles[index] = obstacle;
+        // only the jumps along the obstacle's own row and column can have changed
+        self.update_row_jumps(usize::try_from(point.y).unwrap());
+        self.update_col_jumps(usize::try_from(point.x).unwrap());
+    }
+
+    /// Walks every guard at once, a step or a turn each per tick, until they've all left the map
+    /// or the guards still on it start repeating themselves. A guard turns instead of stepping
+    /// into an obstacle, or into a cell another guard is standing in at the start of the tick.
+    /// `visit` is called with each guard's position when it makes a move and how many ticks it
+    /// then walks straight for, 0 meaning that it turns on the spot, including the step that
+    /// takes it off the map.
+    ///
+    /// While no guard has to turn and every guard is far enough from the others that none of
+    /// them can meet, the guards walk as many ticks at once as the jump tables allow.
+    pub fn patrol(
+        &self,
+        guards: &[DirectedPosition],
+        mut visit: impl FnMut(usize, &DirectedPosition, usize),
+    ) -> Patrolled {
+        let mut current: Vec<Option<DirectedPosition>> = guards.iter().cloned().map(Some).collect();
+        // a repeat has to include a turn, since guards going straight walk off the map, so it's
+        // enough to remember the state at every tick where some guard turns
+        let mut turn_ticks: HashMap<Vec<Option<DirectedPosition>>, usize> = HashMap::new();
+        let mut tick = 0;
+        loop {
+            let active: Vec<(usize, &DirectedPosition)> = current.iter()
+                .enumerate()
+                .filter_map(|(guard_num, guard)| guard.as_ref().map(|guard| (guard_num, guard)))
+                .collect();
+            if active.is_empty() {
+                return Patrolled { ends: vec![PatrolEnd::Exited; guards.len()], repeat: None };
+            }
+            let runs: Vec<usize> = active.iter().map(|(_, guard)| self.run(guard)).collect();
+            // guards get at most 2 closer each tick, and one can only block another from next
+            // to it
+            let closest = active.iter()
+                .enumerate()
+                .flat_map(|(i, (_, guard))| active[i + 1..].iter().map(|(_, other)| distance(guard.pos, other.pos)))
+                .min()
+                .unwrap_or(usize::MAX);
+            let ticks = runs.iter().copied().min().unwrap().min(closest / 2);
+
+            let mut moves = Vec::new();
+            if ticks > 1 {
+                for (&(guard_num, guard), &run) in active.iter().zip(&runs) {
+                    moves.push((guard_num, guard.clone(), ticks, ticks == run && self.jump(guard).is_none()));
+                }
+            } else {
+                for (&(guard_num, guard), &run) in active.iter().zip(&runs) {
+                    let ahead = guard.pos.step(guard.dir);
+                    let blocked = run == 0 || active.iter().any(|&(other_num, other)| other_num != guard_num && other.pos == ahead);
+                    moves.push((guard_num, guard.clone(), usize::from(!blocked), !blocked && run == 1 && self.jump(guard).is_none()));
+                }
+                if moves.iter().any(|&(_, _, ticks, _)| ticks == 0) {
+                    if let Some(&first) = turn_ticks.get(&current) {
+                        let ends = current.iter()
+                            .map(|guard| if guard.is_some() { PatrolEnd::Looped } else { PatrolEnd::Exited })
+                            .collect();
+                        return Patrolled { ends, repeat: Some((first, tick)) };
+                    }
+                    turn_ticks.insert(current.clone(), tick);
+                }
+            }
+            for (guard_num, guard, ticks, exits) in moves {
+                visit(guard_num, &guard, ticks);
+                current[guard_num] = if exits {
+                    None
+                } else if ticks == 0 {
+                    Some(DirectedPosition { pos: guard.pos, dir: guard.dir.rotate_clockwise() })
+                } else {
+                    Some(DirectedPosition { pos: guard.pos.step_by(guard.dir, ticks), dir: guard.dir })
+                };
+            }
+            tick += ticks.max(1);
+        }
+    }
+
+    /// Whether any of the guards ends up walking in a loop forever.
+    pub fn loops(&self, guards: &[DirectedPosition]) -> bool {
+        self.patrol(guards, |_, _, _| {}).ends.contains(&PatrolEnd::Looped)
+    }
+
+    pub fn visited(&self, guards: &[DirectedPosition]) -> HashSet<Point> {
+        let Dimensions { width, length } = self.dimensions;
+        let mut visited = HashSet::new();
+        self.patrol(guards, |_, from, ticks| {
+            visited.insert(from.pos);
+            visited.extend((1..=ticks)
+                .map(|count| from.pos.step_by(from.dir, count))
+                .filter(|pos| pos.in_bounds(width, length)));
+        });
+        visited
+    }
+
+    /// Expands each guard's patrol into the position and direction it's in at every tick, up to
+    /// when it leaves the map, or for guards that get stuck, up to just before the guards all
+    /// first get back into a state they've been in before.
+    pub fn routes(&self, guards: &[DirectedPosition]) -> Vec<Route> {
+        let mut steps = vec![Vec::new(); guards.len()];
+        let patrolled = self.patrol(guards, |guard_num, from, ticks| {
+            if ticks == 0 {
+                steps[guard_num].push(from.clone());
+            }
+            steps[guard_num].extend((0..ticks).map(|count| DirectedPosition { pos: from.pos.step_by(from.dir, count), dir: from.dir }));
+        });
+        let Some((first, repeat)) = patrolled.repeat else {
+            return steps.into_iter().map(|steps| Route { steps, loop_entry: None }).collect();
+        };
+        // the repeat was only noticed at a turn, which may be some way into the loop, and may be
+        // after going round it several times
+        let period = repeat - first;
+        let state = |tick: usize| -> Vec<Option<&DirectedPosition>> {
+            let tick = if tick >= repeat { tick - period } else { tick };
+            steps.iter().map(|steps| steps.get(tick)).collect()
+        };
+        let entry = (0..=first).find(|&tick| state(tick) == state(tick + period)).unwrap();
+        let end = entry + (1..=period).find(|&length| state(entry + length) == state(entry)).unwrap();
+        steps.into_iter()
+            .zip(patrolled.ends)
+            .map(|(mut steps, end_type)| {
+                steps.truncate(end);
+                Route { steps, loop_entry: (end_type == PatrolEnd::Looped).then_some(entry) }
+            })
+            .collect()
+    }
+
+    /// Draws the map with the route marked the same way as the puzzle does: `|` and `-` for
+    /// vertical and horizontal movement, `+` where both happen, and `O` for an added obstacle.
+    pub fn render(
+        &self,
+        guards: &[DirectedPosition],
+        route: &[DirectedPosition],
+        added_obstacle: Option<Point>,
+    ) -> String {
+        let Dimensions { width, length } = self.dimensions;
+        let mut vertical = vec![false; width * length];
+        let mut horizontal = vec![false; width * length];
+        for step in route {
+            match step.dir {
+                Direction::North | Direction::South => vertical[self.index(step.pos)] = true,
+                Direction::East | Direction::West => horizontal[self.index(step.pos)] = true,
+            }
+        }
+        let mut map = String::new();
+        for row in 0..length {
+            for col in 0..width {
+                let point = Point { x: isize::try_from(col).unwrap(), y: isize::try_from(row).unwrap() };
+                let index = self.index(point);
+                let guard = guards.iter().find(|guard| guard.pos == point);
+                map.push(if added_obstacle == Some(point) {
+                    'O'
+                } else if self.obstacles[index] {
+                    '#'
+                } else if let Some(guard) = guard {
+                    guard.dir.tile()
+                } else {
+                    match (vertical[index], horizontal[index]) {
+                        (true, true) => '+',
+                        (true, false) => '|',
+                        (false, true) => '-',
+                        (false, false) => '.',
+                    }
+                });
+            }
+            map.push('\n');
+        }
+        map
+    }
+
+    fn jump(&self, current: &DirectedPosition) -> Option<Point> {
+        self.jumps[dir_index(current.dir)][self.index(current.pos)]
+    }
+
+    /// How many steps the guard can take straight ahead before it has to turn, counting the step
+    /// off the map if nothing stops it first.
+    fn run(&self, guard: &DirectedPosition) -> usize {
+        match self.jump(guard) {
+            Some(stop) => distance(guard.pos, stop),
+            None => distance(guard.pos, self.edge(guard)) + 1,
+        }
+    }
+
+    fn edge(&self, current: &DirectedPosition) -> Point {
+        let width = isize::try_from(self.dimensions.width).unwrap();
+        let length = isize::try_from(self.dimensions.length).unwrap();
+        let Point { x, y } = current.pos;
+        match current.dir {
+            Direction::North => Point { x, y: 0 },
+            Direction::East => Point { x: width - 1, y },
+            Direction::South => Point { x, y: length - 1 },
+            Direction::West => Point { x: 0, y },
+        }
+    }
+
+    fn update_row_jumps(&mut self, row: usize) {
+        let y = isize::try_from(row).unwrap();
+        let width = self.dimensions.width;
+        let mut east_stop = None;
+        for col in (0..width).rev() {
+            let x = isize::try_from(col).unwrap();
+            self.jumps[dir_index(Direction::East)][row * width + col] = east_stop;
+            if self.obstacles[row * width + col] {
+                east_stop = Some(Point { x: x - 1, y });
+            }
+        }
+        let mut west_stop = None;
+        for col in 0..width {
+            let x = isize::try_from(col).unwrap();
+            self.jumps[dir_index(Direction::West)][row * width + col] = west_stop;
+            if self.obstacles[row * width + col] {
+                west_stop = Some(Point { x: x + 1, y });
+            }
+        }
+    }
+
+    fn update_col_jumps(&mut self, col: usize) {
+        let x = isize::try_from(col).unwrap();
+        let width = self.dimensions.width;
+        let mut south_stop = None;
+        for row in (0..self.dimensions.length).rev() {
+            let y = isize::try_from(row).unwrap();
+            self.jumps[dir_index(Direction::South)][row * width + col] = south_stop;
+            if self.obstacles[row * width + col] {
+                south_stop = Some(Point { x, y: y - 1 });
+            }
+        }
+        let mut north_stop = None;
+        for row in 0..self.dimensions.length {
+            let y = isize::try_from(row).unwrap();
+            self.jumps[dir_index(Direction::North)][row * width + col] = north_stop;
+            if self.obstacles[row * width + col] {
+                north_stop = Some(Point { x, y: y + 1 });
+            }
+        }
+    }
+
+    fn index(&self, point: Point) -> usize {
+        usize::try_from(point.y).unwrap() * self.dimensions.width + usize::try_from(point.x).unwrap()
+    }
+}
+
+fn distance(from: Point, to: Point) -> usize {
+    (to.x - from.x).unsigned_abs() + (to.y - from.y).unsigned_abs()
+}
+
+fn dir_index(dir: Direction) -> usize {
+    match dir {
+        Direction::North => 0,
+        Direction::East => 1,
+        Direction::South => 2,
+        Direction::West => 3,
+    }
+}
+
+#[derive(Eq, PartialEq, Copy, Clone)]
+pub enum PatrolEnd { Exited, Looped }
+
+/// How each guard's patrol ended, and if any got stuck, the ticks at which the guards were first
+/// seen in the state they repeated and at which they got back to it.
+pub struct Patrolled {
+    pub ends: Vec<PatrolEnd>,
+    pub repeat: Option<(usize, usize)>,
+}
+
+pub struct Route {
+    pub steps: Vec<DirectedPosition>,
+    pub loop_entry: Option<usize>,
+}
+
+impl Route {
+    pub fn cycle(&self) -> Option<&[DirectedPosition]> {
+        self.loop_entry.map(|entry| &self.steps[entry..])
+    }
+}
+
+#[derive(Eq, PartialEq, Hash, Clone)]
+pub struct DirectedPosition { pub pos: Point, pub dir: Direction }
@@ -1,5 +1,7 @@
-use crate::common::DaySpec;
-use num::pow;
+mod operator;
+
+use crate::common::{DaySpec, Export};
+use crate::day_07::operator::{LeftOperands, Operator, OperatorSet, Value};
 
 pub const DAY_SEVEN: DaySpec<u64, u64> = DaySpec {
     day_num: 7,
@@ -7,55 +9,188 @@ pub const DAY_SEVEN: DaySpec<u64, u64> = DaySpec {
     part_1,
     part_2_name: "total calibration result (including concat)",
     part_2,
+    exports: &[
+        Export {
+            name: "solutions",
+            description: "operators that solve each equation; pass an operator set such as \
+                \"+ * || - / ^\", and \"all\" to list every solution rather than the first",
+            run: export_solutions,
+            needs_input: true,
+        },
+    ],
 };
 
 fn part_1(input: &str) -> u64 {
-    parse_input(input).iter_mut()
-        .map(|equation| {
-            if has_solution(equation.test, &mut equation.numbers, false) {
-                Some(equation.test)
-            } else {
-                None
-            }
-        })
-        .filter(|test| test.is_some())
-        .map(Option::unwrap)
-        .sum()
+    total_calibration_result(input, &OperatorSet::parse("+ *"))
 }
 
 fn part_2(input: &str) -> u64 {
-    parse_input(input).iter_mut()
-        .map(|equation| {
-            if has_solution(equation.test, &mut equation.numbers, true) {
-                Some(equation.test)
-            } else {
-                None
-            }
-        })
-        .filter(|test| test.is_some())
-        .map(Option::unwrap)
-        .sum()
+    total_calibration_result(input, &OperatorSet::parse("+ * ||"))
 }
 
-fn has_solution(test: u64, numbers: &mut Vec<u64>, with_concat: bool) -> bool {
-    let last = numbers.pop().unwrap();
-    let solution_found = if numbers.is_empty() {
-        test == last
-    } else {
-        (with_concat && last_matches(test, last) && has_solution(remove_last(test, last), numbers, true)) ||
-            (test % last == 0 && has_solution(test / last, numbers, with_concat)) ||
-            has_solution(test - last, numbers, with_concat)
-    };
-    numbers.push(last);
-    solution_found
+fn total_calibration_result(input: &str, operators: &OperatorSet) -> u64 {
+    let total: Value = parse_input(input).iter()
+        .filter(|equation| !solutions(equation, operators, 1).is_empty())
+        .map(|equation| equation.test)
+        .sum();
+    u64::try_from(total).unwrap()
 }
 
-fn last_matches(test: u64, last: u64) -> bool {
-    test.to_string().ends_with(&last.to_string())
+/// Finds up to `limit` sequences of operators that make the equation's numbers evaluate to its
+/// test value, each one listed in the order the operators appear in the equation.
+fn solutions(equation: &Equation, operators: &OperatorSet, limit: usize) -> Vec<Vec<Operator>> {
+    let mut solutions = Vec::new();
+    let reachable = reachable_bounds(&equation.numbers, operators);
+    let numbers = &equation.numbers;
+    solve(equation.test, numbers, &reachable, operators, &mut Vec::new(), &mut solutions, limit);
+    solutions
+}
+
+// works backwards from the test value, undoing the last operation each time; reachable holds the
+// bounds of what each run of the numbers from the start can evaluate to, so that values outside
+// them are given up on straight away, and reversed_operators holds the operators undone so far,
+// starting from the end of the equation
+fn solve(
+    test: Value,
+    numbers: &[Value],
+    reachable: &[Option<(Value, Value)>],
+    operators: &OperatorSet,
+    reversed_operators: &mut Vec<Operator>,
+    solutions: &mut Vec<Vec<Operator>>,
+    limit: usize,
+) {
+    if !reachable[numbers.len() - 1].is_some_and(|(low, high)| (low..=high).contains(&test)) {
+        return;
+    }
+    let (&last, rest) = numbers.split_last().unwrap();
+    if rest.is_empty() {
+        if test == last {
+            solutions.push(reversed_operators.iter().rev().copied().collect());
+        }
+        return;
+    }
+    for &operator in operators.operators() {
+        if solutions.len() >= limit {
+            return;
+        }
+        reversed_operators.push(operator);
+        match (operator.inverse)(test, last) {
+            LeftOperands::Values(lefts) => {
+                for left in lefts {
+                    solve(left, rest, reachable, operators, reversed_operators, solutions, limit);
+                }
+            },
+            LeftOperands::Range(low, high) => {
+                // the left operand has to be something the rest of the numbers evaluate to, so
+                // the range only needs to cover what they can reach (which keeps it from spanning
+                // most of the values there are), and when there are still fewer ways of
+                // evaluating them than values in the range, it's quicker to try each of those
+                let Some((reachable_low, reachable_high)) = reachable[rest.len() - 1] else {
+                    reversed_operators.pop();
+                    continue;
+                };
+                let (low, high) = (low.max(reachable_low), high.min(reachable_high));
+                let evaluations = u32::try_from(rest.len() - 1).ok()
+                    .and_then(|exponent| operators.operators().len().checked_pow(exponent));
+                let range_size = high.abs_diff(low).checked_add(1);
+                let evaluate_forwards = match (evaluations, range_size) {
+                    (Some(evaluations), Some(range_size)) => (evaluations as u128) < range_size,
+                    (evaluations, _) => evaluations.is_some(),
+                };
+                if evaluate_forwards {
+                    evaluate_all(rest[0], &rest[1..], operators, &mut Vec::new(), &mut |prefix, left| {
+                        if (low..=high).contains(&left) && solutions.len() < limit {
+                            solutions.push(
+                                prefix.iter().copied()
+                                    .chain(reversed_operators.iter().rev().copied())
+                                    .collect()
+                            );
+                        }
+                        solutions.len() < limit
+                    });
+                } else if low <= high {
+                    for left in low..=high {
+                        if solutions.len() >= limit {
+                            break;
+                        }
+                        solve(left, rest, reachable, operators, reversed_operators, solutions, limit);
+                    }
+                }
+            },
+            LeftOperands::Any => {
+                // whatever the rest of the numbers evaluate to, this operator gives the test
+                // value, so any way of evaluating them at all is a solution
+                evaluate_all(rest[0], &rest[1..], operators, &mut Vec::new(), &mut |prefix, _| {
+                    if solutions.len() < limit {
+                        solutions.push(
+                            prefix.iter().copied()
+                                .chain(reversed_operators.iter().rev().copied())
+                                .collect()
+                        );
+                    }
+                    solutions.len() < limit
+                });
+            },
+        }
+        reversed_operators.pop();
+    }
+}
+
+// for each run of the numbers from the start, the smallest and largest values any way of
+// evaluating it could give, or None if none of them evaluate at all; not every value in between
+// need be reachable
+fn reachable_bounds(numbers: &[Value], operators: &OperatorSet) -> Vec<Option<(Value, Value)>> {
+    let mut bounds = vec![Some((numbers[0], numbers[0]))];
+    for &next in &numbers[1..] {
+        let previous = bounds[bounds.len() - 1];
+        bounds.push(previous.and_then(|(low, high)| {
+            operators.operators().iter()
+                .filter_map(|operator| (operator.bounds)(low, high, next))
+                .reduce(|(low, high), (other_low, other_high)| (low.min(other_low), high.max(other_high)))
+        }));
+    }
+    bounds
+}
+
+// calls found with each operator sequence that evaluates the numbers without overflowing (or
+// dividing by zero) and what it evaluates them to, until it returns false
+fn evaluate_all(
+    value: Value,
+    numbers: &[Value],
+    operators: &OperatorSet,
+    applied: &mut Vec<Operator>,
+    found: &mut impl FnMut(&[Operator], Value) -> bool,
+) -> bool {
+    let Some((&next, rest)) = numbers.split_first() else {
+        return found(applied, value);
+    };
+    for &operator in operators.operators() {
+        if let Some(new_value) = (operator.apply)(value, next) {
+            applied.push(operator);
+            let keep_going = evaluate_all(new_value, rest, operators, applied, found);
+            applied.pop();
+            if !keep_going {
+                return false;
+            }
+        }
+    }
+    true
 }
 
-fn remove_last(test: u64, last: u64) -> u64 {
-    test / pow(10, last.to_string().len())
+fn export_solutions(input: &str, args: &[String]) -> String {
+    let operators = OperatorSet::parse(args.first().map_or("+ * ||", String::as_str));
+    let limit = if args.get(1).is_some_and(|arg| arg == "all") { usize::MAX } else { 1 };
+    let mut output = String::new();
+    for equation in parse_input(input) {
+        for solution in solutions(&equation, &operators, limit) {
+            output.push_str(&format!("{} = {}", equation.test, equation.numbers[0]));
+            for (operator, number) in solution.iter().zip(&equation.numbers[1..]) {
+                output.push_str(&format!(" {} {}", operator.symbol, number));
+            }
+            output.push('\n');
+        }
+    }
+    output
 }
 
 fn parse_input(input: &str) -> Vec<Equation> {
@@ -76,8 +211,8 @@ fn parse_equation(line: &str) -> Equation {
 }
 
 struct Equation {
-    test: u64,
-    numbers: Vec<u64>,
+    test: Value,
+    numbers: Vec<Value>,
 }
 
 #[cfg(test)]
@@ -103,4 +238,65 @@ mod tests {
     fn part_2_sample() {
         assert_eq!(11387, part_2(INPUT));
     }
+
+    #[test]
+    fn export_solutions_sample() {
+        let args = [String::from("+ *"), String::from("all")];
+        assert_eq!("190 = 10 * 19
+3267 = 81 * 40 + 27
+3267 = 81 + 40 * 27
+292 = 11 + 6 * 16 + 20
+", export_solutions(INPUT, &args));
+        assert!(export_solutions(INPUT, &[]).contains("7290 = 6 * 8 || 6 * 15\n"));
+    }
+
+    fn solution_symbols(line: &str, spec: &str) -> Vec<String> {
+        solutions(&parse_equation(line), &OperatorSet::parse(spec), usize::MAX).iter()
+            .map(|solution| {
+                solution.iter()
+                    .map(|operator| operator.symbol)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn extended_operators() {
+        assert_eq!(vec!["/ -"], solution_symbols("12: 100 7 2", "- / ^"));
+        assert_eq!(vec!["/"], solution_symbols("7: 15 2", "- / ^"));
+        // 3 - 9 = -6, and squaring it needs the negative root when working backwards
+        assert_eq!(vec!["- ^"], solution_symbols("36: 3 9 2", "- / ^"));
+        // anything to the power 0 is 1, so every way of evaluating 5 and 3 first works
+        assert_eq!(vec!["- ^", "^ ^"], solution_symbols("1: 5 3 0", "- ^"));
+    }
+
+    #[test]
+    fn large_values() {
+        // i128::MAX, which can only be reached by concatenation
+        assert_eq!(
+            vec!["||"],
+            solution_symbols("170141183460469231731687303715884105727: 17014118346046923173 1687303715884105727", "+ * ||"),
+        );
+        // and anything that would overflow just isn't a solution
+        assert!(solution_symbols("1: 170141183460469231731687303715884105727 2 0", "* -").is_empty());
+        // dividing by a huge number gives zero for a huge range of left operands, which is only
+        // checked against what the rest of the numbers can evaluate to
+        assert_eq!(vec!["/"], solution_symbols("0: 7 170141183460469231731687303715884105727", "/"));
+        assert_eq!(vec!["+ /", "- /", "/ /"], solution_symbols("0: 3 4 1000000000000000000000", "+ - /"));
+        // and the range's ends or the divisor's size not fitting in a value doesn't overflow
+        assert_eq!(vec!["/"], solution_symbols("1: -170141183460469231731687303715884105728 -170141183460469231731687303715884105728", "/"));
+        assert!(solution_symbols("-1: 5 -170141183460469231731687303715884105728", "/").is_empty());
+        assert!(solution_symbols("2: 5 170141183460469231731687303715884105727", "/").is_empty());
+    }
+
+    #[test]
+    fn huge_range_with_many_numbers() {
+        // too many ways of evaluating the zeros to count, and a range of nearly 2 * 10^30 left
+        // operands, but the zeros can only ever evaluate to zero
+        let line = format!("0: {} 1000000000000000000000000000000", vec!["0"; 50].join(" "));
+        let found = solutions(&parse_equation(&line), &OperatorSet::parse("+ - /"), 1);
+        let symbols: Vec<_> = found[0].iter().map(|operator| operator.symbol).collect();
+        assert_eq!(format!("{} /", vec!["+"; 49].join(" ")), symbols.join(" "));
+    }
 }
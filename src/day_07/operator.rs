@@ -0,0 +1,198 @@
+pub type Value = i128;
+
+/// A binary operator that equations are evaluated with, strictly left to right. As well as
+/// applying it, solving needs its inverse: every left operand that gives `result` with the given
+/// right operand; and its bounds: the smallest and largest results it could give for any left
+/// operand from `low` to `high` with the given right operand (saturating rather than overflowing),
+/// or `None` if none of them give a result at all.
+#[derive(Copy, Clone)]
+pub struct Operator {
+    pub symbol: &'static str,
+    pub apply: fn(Value, Value) -> Option<Value>,
+    pub inverse: fn(Value, Value) -> LeftOperands,
+    pub bounds: fn(Value, Value, Value) -> Option<(Value, Value)>,
+}
+
+pub enum LeftOperands {
+    Values(Vec<Value>),
+    /// Every value from the first to the second inclusive.
+    Range(Value, Value),
+    Any,
+}
+
+pub const ADD: Operator = Operator {
+    symbol: "+",
+    apply: |left, right| left.checked_add(right),
+    inverse: |result, right| LeftOperands::from(result.checked_sub(right)),
+    bounds: |low, high, right| Some((low.saturating_add(right), high.saturating_add(right))),
+};
+
+pub const MULTIPLY: Operator = Operator {
+    symbol: "*",
+    apply: |left, right| left.checked_mul(right),
+    inverse: |result, right| {
+        if right == 0 {
+            if result == 0 { LeftOperands::Any } else { LeftOperands::Values(Vec::new()) }
+        } else if result.checked_rem(right) == Some(0) {
+            LeftOperands::from(result.checked_div(right))
+        } else {
+            LeftOperands::Values(Vec::new())
+        }
+    },
+    bounds: |low, high, right| ordered(low.saturating_mul(right), high.saturating_mul(right)),
+};
+
+pub const CONCAT: Operator = Operator {
+    symbol: "||",
+    apply: |left, right| {
+        if left < 0 || right < 0 {
+            return None;
+        }
+        left.checked_mul(digit_multiplier(right)?)?.checked_add(right)
+    },
+    inverse: |result, right| {
+        let Some(multiplier) = digit_multiplier(right) else {
+            return LeftOperands::Values(Vec::new());
+        };
+        if right < 0 || result < right || (result - right) % multiplier != 0 {
+            return LeftOperands::Values(Vec::new());
+        }
+        LeftOperands::Values(vec![(result - right) / multiplier])
+    },
+    bounds: |low, high, right| {
+        if high < 0 || right < 0 {
+            return None;
+        }
+        let multiplier = digit_multiplier(right)?;
+        Some((
+            low.max(0).saturating_mul(multiplier).saturating_add(right),
+            high.saturating_mul(multiplier).saturating_add(right),
+        ))
+    },
+};
+
+pub const SUBTRACT: Operator = Operator {
+    symbol: "-",
+    apply: |left, right| left.checked_sub(right),
+    inverse: |result, right| LeftOperands::from(result.checked_add(right)),
+    bounds: |low, high, right| Some((low.saturating_sub(right), high.saturating_sub(right))),
+};
+
+/// Integer division, rounding towards zero.
+pub const DIVIDE: Operator = Operator {
+    symbol: "/",
+    apply: |left, right| left.checked_div(right),
+    inverse: |result, right| {
+        if right == 0 {
+            return LeftOperands::Values(Vec::new());
+        }
+        // left / right == result for a run of |right| - 1 values of left either side of
+        // result * right, on whichever side keeps left the same sign as result * right (or on
+        // both sides, when result is zero); if result * right doesn't fit in a value then none
+        // of the run does, and otherwise the run is cut short where values run out
+        let Some(exact) = result.checked_mul(right) else {
+            return LeftOperands::Values(Vec::new());
+        };
+        let spread = Value::try_from(right.unsigned_abs() - 1).unwrap();
+        let (low, high) = match exact.signum() {
+            1 => (exact, exact.saturating_add(spread)),
+            -1 => (exact.saturating_sub(spread), exact),
+            _ => (-spread, spread),
+        };
+        LeftOperands::Range(low, high)
+    },
+    bounds: |low, high, right| {
+        if right == 0 {
+            return None;
+        }
+        ordered(low.saturating_div(right), high.saturating_div(right))
+    },
+};
+
+pub const POWER: Operator = Operator {
+    symbol: "^",
+    apply: |left, right| left.checked_pow(u32::try_from(right).ok()?),
+    inverse: |result, right| {
+        let Ok(exponent) = u32::try_from(right) else {
+            return LeftOperands::Values(Vec::new());
+        };
+        if exponent == 0 {
+            return if result == 1 { LeftOperands::Any } else { LeftOperands::Values(Vec::new()) };
+        }
+        let root = integer_root(result.unsigned_abs(), exponent);
+        let mut lefts: Vec<Value> = [root, -root].into_iter()
+            .filter(|&left| left.checked_pow(exponent) == Some(result))
+            .collect();
+        lefts.dedup();
+        LeftOperands::Values(lefts)
+    },
+    bounds: |low, high, right| {
+        let exponent = u32::try_from(right).ok()?;
+        if exponent % 2 == 1 {
+            return Some((low.saturating_pow(exponent), high.saturating_pow(exponent)));
+        }
+        // an even power only depends on the left operand's size, which is smallest at zero
+        let (near, far) = if low <= 0 && high >= 0 {
+            (0, low.unsigned_abs().max(high.unsigned_abs()))
+        } else {
+            ordered(low.unsigned_abs(), high.unsigned_abs())?
+        };
+        let saturating_pow = |size: u128| Value::try_from(size.saturating_pow(exponent)).unwrap_or(Value::MAX);
+        Some((saturating_pow(near), saturating_pow(far)))
+    },
+};
+
+const BUILT_IN: [Operator; 6] = [ADD, MULTIPLY, CONCAT, SUBTRACT, DIVIDE, POWER];
+
+impl From<Option<Value>> for LeftOperands {
+    fn from(left: Option<Value>) -> Self {
+        LeftOperands::Values(left.into_iter().collect())
+    }
+}
+
+pub struct OperatorSet {
+    operators: Vec<Operator>,
+}
+
+impl OperatorSet {
+    pub fn new(operators: Vec<Operator>) -> Self {
+        OperatorSet { operators }
+    }
+
+    /// Builds a set from space-separated operator symbols, e.g. `"+ * ||"`.
+    pub fn parse(spec: &str) -> Self {
+        let operators = spec.split_whitespace()
+            .map(|symbol| {
+                *BUILT_IN.iter()
+                    .find(|operator| operator.symbol == symbol)
+                    .unwrap_or_else(|| panic!("unknown operator: {}", symbol))
+            })
+            .collect();
+        OperatorSet::new(operators)
+    }
+
+    pub fn operators(&self) -> &[Operator] {
+        &self.operators
+    }
+}
+
+fn ordered<T: Ord>(first: T, second: T) -> Option<(T, T)> {
+    Some(if first <= second { (first, second) } else { (second, first) })
+}
+
+fn digit_multiplier(value: Value) -> Option<Value> {
+    let digits = if value == 0 { 1 } else { value.unsigned_abs().ilog10() + 1 };
+    10_i128.checked_pow(digits)
+}
+
+fn integer_root(value: u128, exponent: u32) -> Value {
+    // start from the floating point estimate, then correct it for any rounding error
+    let mut root = (value as f64).powf(1.0 / exponent as f64).round() as i128;
+    while root > 0 && root.checked_pow(exponent).is_none_or(|power| power.unsigned_abs() > value) {
+        root -= 1;
+    }
+    while (root + 1).checked_pow(exponent).is_some_and(|power| power.unsigned_abs() <= value) {
+        root += 1;
+    }
+    root
+}
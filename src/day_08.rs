@@ -1,5 +1,7 @@
-use std::collections::{HashMap, HashSet};
-use crate::common::{DaySpec, Dimensions, Point};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use num::integer::gcd;
+use num::rational::Ratio;
+use crate::common::{DaySpec, Dimensions, Export, Point};
 
 pub const DAY_EIGHT: DaySpec<usize, usize> = DaySpec {
     day_num: 8,
@@ -7,79 +9,209 @@ pub const DAY_EIGHT: DaySpec<usize, usize> = DaySpec {
     part_1,
     part_2_name: "actual antinode locations",
     part_2,
+    exports: &[
+        Export {
+            name: "antinodes",
+            description: "map of antinodes with per-frequency counts; pass \"multiples\" for \
+                part 2's rule, \"collinear\" for every point in line, or a comma-separated list \
+                of distance ratios such as 2,3/2 (default 2), followed by \"between\" to include \
+                the points between the antennas",
+            run: export_antinodes,
+            needs_input: true,
+        },
+    ],
 };
 
 fn part_1(input: &str) -> usize {
     let (antennas, dimensions) = parse_input(input);
-    calculate_antinodes(antennas, dimensions, true, |_, _, _| false)
+    Antinodes::find(antennas, dimensions, &AntinodeRule::puzzle_harmonics()).total()
 }
 
 fn part_2(input: &str) -> usize {
     let (antennas, dimensions) = parse_input(input);
-    calculate_antinodes(antennas, dimensions, false, Point::in_bounds)
+    Antinodes::find(antennas, dimensions, &AntinodeRule::Multiples).total()
 }
 
-fn calculate_antinodes(
-    antennas: HashMap<char, Vec<Point>>,
+enum AntinodeRule {
+    /// Grid points in line with two antennas of the same frequency, where one antenna is the
+    /// given ratio times as far away as the other. These lie beyond the antennas, and also
+    /// between them if `between` is set.
+    Harmonics { ratios: Vec<Ratio<isize>>, between: bool },
+    /// Grid points in line with two antennas of the same frequency, a whole number of times
+    /// the distance between them from either one, which includes the antennas themselves.
+    Multiples,
+    /// Every grid point in line with two antennas of the same frequency.
+    Collinear,
+}
+
+impl AntinodeRule {
+    /// Part 1's rule: one antenna twice as far away as the other, beyond them.
+    fn puzzle_harmonics() -> Self {
+        AntinodeRule::Harmonics { ratios: vec![Ratio::from_integer(2)], between: false }
+    }
+}
+
+struct Antinodes {
     dimensions: Dimensions,
-    skip_self_antinode: bool,
-    continue_func: fn(&Point, usize, usize) -> bool
-) -> usize {
-    let mut antinodes = HashSet::new();
-    for (_, antennas_at_freq) in antennas.iter() {
-        for i in 0..antennas_at_freq.len() {
-            for j in 0..antennas_at_freq.len() {
-                if i == j { continue; }
-                let antenna_i = *antennas_at_freq.get(i).unwrap();
-                let antenna_j = *antennas_at_freq.get(j).unwrap();
-                let antinodes_i_j = calculate_for_antenna(
-                    antenna_i,
-                    antenna_j,
-                    skip_self_antinode,
-                    continue_func,
-                    dimensions,
-                );
-                let antinodes_j_i = calculate_for_antenna(
-                    antenna_j,
-                    antenna_i,
-                    skip_self_antinode,
-                    continue_func,
-                    dimensions,
-                );
-                for antinode in antinodes_i_j.into_iter().chain(antinodes_j_i.into_iter()) {
-                    antinodes.insert(antinode);
+    antennas: HashMap<char, Vec<Point>>,
+    by_frequency: BTreeMap<char, HashSet<Point>>,
+}
+
+impl Antinodes {
+    fn find(antennas: HashMap<char, Vec<Point>>, dimensions: Dimensions, rule: &AntinodeRule) -> Self {
+        let mut by_frequency = BTreeMap::new();
+        for (&frequency, antennas_at_freq) in antennas.iter() {
+            let antinodes: &mut HashSet<Point> = by_frequency.entry(frequency).or_default();
+            for (i, &antenna_i) in antennas_at_freq.iter().enumerate() {
+                for &antenna_j in &antennas_at_freq[i + 1..] {
+                    match rule {
+                        AntinodeRule::Harmonics { ratios, between } => {
+                            for &ratio in ratios {
+                                let harmonics = harmonic_antinodes(antenna_i, antenna_j, ratio, *between, dimensions);
+                                antinodes.extend(harmonics);
+                            }
+                        },
+                        AntinodeRule::Multiples => {
+                            antinodes.extend(line_antinodes(antenna_i, antenna_j, false, dimensions));
+                        },
+                        AntinodeRule::Collinear => {
+                            antinodes.extend(line_antinodes(antenna_i, antenna_j, true, dimensions));
+                        },
+                    }
                 }
             }
         }
+        Antinodes { dimensions, antennas, by_frequency }
+    }
+
+    fn total(&self) -> usize {
+        self.by_frequency.values()
+            .flatten()
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Locations that are antinodes for more than one frequency, and antinodes that are at the
+    /// same location as an antenna (of any frequency).
+    fn overlaps(&self) -> (usize, usize) {
+        let mut frequency_counts: HashMap<Point, usize> = HashMap::new();
+        for &antinode in self.by_frequency.values().flatten() {
+            *frequency_counts.entry(antinode).or_insert(0) += 1;
+        }
+        let antenna_locations: HashSet<Point> = self.antennas.values()
+            .flatten()
+            .copied()
+            .collect();
+        let shared = frequency_counts.values()
+            .filter(|&&count| count > 1)
+            .count();
+        let on_antennas = frequency_counts.keys()
+            .filter(|antinode| antenna_locations.contains(antinode))
+            .count();
+        (shared, on_antennas)
+    }
+
+    /// Draws the map in the puzzle's notation: antennas are shown by their frequency, and any
+    /// other antinode locations as `#`.
+    fn render(&self) -> String {
+        let Dimensions { width, length } = self.dimensions;
+        let mut map = vec![vec!['.'; width]; length];
+        for &Point { x, y } in self.by_frequency.values().flatten() {
+            map[usize::try_from(y).unwrap()][usize::try_from(x).unwrap()] = '#';
+        }
+        for (&frequency, antennas_at_freq) in self.antennas.iter() {
+            for &Point { x, y } in antennas_at_freq {
+                map[usize::try_from(y).unwrap()][usize::try_from(x).unwrap()] = frequency;
+            }
+        }
+        map.into_iter()
+            .map(|row| row.into_iter().collect::<String>() + "\n")
+            .collect()
     }
-    antinodes.len()
 }
 
-fn calculate_for_antenna(
+fn harmonic_antinodes(
     first_antenna: Point,
     second_antenna: Point,
-    skip_self_antinode: bool,
-    continue_func: fn(&Point, usize, usize) -> bool,
+    ratio: Ratio<isize>,
+    between: bool,
     dimensions: Dimensions,
 ) -> Vec<Point> {
+    // any point in line with the antennas is first + t * (second - first) for some t, which is
+    // ratio times as far from first as from second when |t| = ratio * |t - 1|; as the antennas
+    // are a pair, the reciprocal ratio covers the other antenna being the further one
+    let one = Ratio::from_integer(1);
+    let mut positions = Vec::new();
+    for ratio in [ratio, ratio.recip()] {
+        if ratio != one {
+            positions.push(ratio / (ratio - one));
+        }
+        if between {
+            positions.push(ratio / (ratio + one));
+        }
+    }
+    let period = second_antenna - first_antenna;
     let mut antinodes = Vec::new();
-    let period = first_antenna - second_antenna;
-    let mut current_antinode = first_antenna;
-    if skip_self_antinode {
-        current_antinode = current_antinode + period;
+    for t in positions {
+        let (numer, denom) = (*t.numer(), *t.denom());
+        if period.x % denom == 0 && period.y % denom == 0 {
+            let antinode = first_antenna + Point { x: period.x / denom * numer, y: period.y / denom * numer };
+            if antinode.in_bounds(dimensions.width, dimensions.length) {
+                antinodes.push(antinode);
+            }
+        }
     }
-    let Dimensions { width, length } = dimensions;
-    let mut has_run_once = false;
-    while !has_run_once || continue_func(&current_antinode, width, length) {
-        if current_antinode.in_bounds(width, length) {
-            antinodes.push(current_antinode);
+    antinodes
+}
+
+/// Grid points along the line through the antennas, stepping from the first antenna by the
+/// distance between them, or if `reduced` is set by the smallest step that stays on the grid.
+fn line_antinodes(first_antenna: Point, second_antenna: Point, reduced: bool, dimensions: Dimensions) -> Vec<Point> {
+    let period = second_antenna - first_antenna;
+    let divisor = if reduced { gcd(period.x, period.y) } else { 1 };
+    let step = Point { x: period.x / divisor, y: period.y / divisor };
+    let mut antinodes = Vec::new();
+    for (start, step) in [(first_antenna, step), (first_antenna - step, Point { x: -step.x, y: -step.y })] {
+        let mut antinode = start;
+        while antinode.in_bounds(dimensions.width, dimensions.length) {
+            antinodes.push(antinode);
+            antinode = antinode + step;
         }
-        current_antinode = current_antinode + period;
-        has_run_once = true;
     }
     antinodes
 }
 
+fn export_antinodes(input: &str, args: &[String]) -> String {
+    let (antennas, dimensions) = parse_input(input);
+    let between = match args.get(1).map(String::as_str) {
+        Some("between") => true,
+        Some(arg) => panic!("unknown option: {}", arg),
+        None => false,
+    };
+    let rule = match args.first().map(String::as_str) {
+        Some("multiples") => AntinodeRule::Multiples,
+        Some("collinear") => AntinodeRule::Collinear,
+        Some(ratios) => AntinodeRule::Harmonics {
+            ratios: ratios.split(',')
+                .map(|ratio| ratio.parse().unwrap_or_else(|_| panic!("invalid ratio: {}", ratio)))
+                .collect(),
+            between,
+        },
+        None => AntinodeRule::puzzle_harmonics(),
+    };
+    let antinodes = Antinodes::find(antennas, dimensions, &rule);
+    let mut output = antinodes.render();
+    output.push('\n');
+    for (frequency, antinodes_at_freq) in &antinodes.by_frequency {
+        output.push_str(&format!("{}: {} antinodes\n", frequency, antinodes_at_freq.len()));
+    }
+    let (shared, on_antennas) = antinodes.overlaps();
+    output.push_str(&format!("total: {} antinodes\n", antinodes.total()));
+    output.push_str(&format!("shared by more than one frequency: {}\n", shared));
+    output.push_str(&format!("on top of an antenna: {}\n", on_antennas));
+    output
+}
+
 fn parse_input(input: &str) -> (HashMap<char, Vec<Point>>, Dimensions) {
     let mut antennas = HashMap::new();
     let mut width = 0;
@@ -128,4 +260,54 @@ mod tests {
     fn part_2_sample() {
         assert_eq!(34, part_2(INPUT));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn export_antinodes_sample() {
+        let output = export_antinodes(INPUT, &[String::from("collinear")]);
+        assert!(output.starts_with("##....#....#
+.#.#....0...
+..#.#0....#.
+..##...0....
+....0....#..
+.#...#A....#
+...#..#.....
+#....#.#....
+..#.....A...
+....#....A..
+.#........#.
+...#......##
+"));
+        assert!(output.ends_with("0: 21 antinodes
+A: 16 antinodes
+total: 34 antinodes
+shared by more than one frequency: 3
+on top of an antenna: 7
+"));
+        assert_eq!(output, export_antinodes(INPUT, &[String::from("multiples")]));
+        assert!(export_antinodes(INPUT, &[]).contains("total: 14 antinodes"));
+    }
+
+    #[test]
+    fn exact_antinodes() {
+        let dimensions = Dimensions { width: 10, length: 10 };
+        let first = Point { x: 0, y: 0 };
+        let second = Point { x: 6, y: 3 };
+        // the puzzle's ratio of 2 gives the points a third of the way between the antennas as
+        // well, when they land on the grid and the points between are asked for
+        assert!(harmonic_antinodes(first, second, Ratio::from_integer(2), false, dimensions).is_empty());
+        let harmonics = harmonic_antinodes(first, second, Ratio::from_integer(2), true, dimensions);
+        assert!(harmonics.contains(&Point { x: 2, y: 1 }));
+        assert!(harmonics.contains(&Point { x: 4, y: 2 }));
+        assert_eq!(2, harmonics.len());
+        // and the whole line, not just whole multiples of the distance between the antennas
+        assert_eq!(2, line_antinodes(first, second, false, dimensions).len());
+        assert_eq!(5, line_antinodes(first, second, true, dimensions).len());
+
+        // the parts keep to whole multiples, so only the antennas themselves count
+        let input = "a.........\n..........\n..........\n......a...\n";
+        assert_eq!(0, part_1(input));
+        assert_eq!(2, part_2(input));
+        assert!(export_antinodes(input, &[String::from("2"), String::from("between")]).contains("total: 2 antinodes"));
+        assert!(export_antinodes(input, &[String::from("collinear")]).contains("total: 4 antinodes"));
+    }
+}
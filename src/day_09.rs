@@ -1,112 +1,73 @@
-use crate::common::DaySpec;
+mod disk;
+mod strategy;
 
-pub const DAY_NINE: DaySpec<u64, u64> = DaySpec {
+use crate::common::{DaySpec, Export};
+use crate::day_09::disk::Disk;
+use crate::day_09::strategy::{BlockByBlock, CompactionStrategy, FirstFit, STRATEGIES, compare};
+
+pub const DAY_NINE: DaySpec<u128, u128> = DaySpec {
     day_num: 9,
     part_1_name: "filesystem checksum",
     part_1,
     part_2_name: "filesystem checksum without fragmentation",
     part_2,
+    exports: &[
+        Export {
+            name: "compact",
+            description: "compare compaction strategies by moves, fragmentation and layout; pass \
+                strategy names to pick which (blocks, first-fit, best-fit, worst-fit, \
+                first-fit-anywhere, stable-order)",
+            run: export_compact,
+            needs_input: true,
+        },
+    ],
 };
 
-fn part_1(input: &str) -> u64 {
-    let mut disk_contents = parse_input(input);
-    let mut start = 0;
-    let mut end = disk_contents.len() - 1;
-    while start < end {
-        match (disk_contents[start], disk_contents[end]) {
-            (_, None) => end -= 1,
-            (Some(_), _) => start += 1,
-            (None, Some(_)) => {
-                disk_contents[start] = disk_contents[end];
-                disk_contents[end] = None;
-                end -= 1;
-                start += 1;
-            }
-        }
-    }
-    generate_checksum(&disk_contents)
+fn part_1(input: &str) -> u128 {
+    compacted_checksum(input, &BlockByBlock)
 }
 
-fn part_2(input: &str) -> u64 {
-    let mut disk_contents = parse_input(input);
-    let mut start = 0;
-    let mut end = disk_contents.len() - 1;
-    let mut file_id = None;
-    let mut file_size = 0;
-    while start < end {
-        if let None = file_id {
-            match (disk_contents[start], disk_contents[end]) {
-                (_, None) => end -= 1,
-                (Some(_), _) => start += 1,
-                (None, Some(_)) => file_id = disk_contents[end],
-            }
-        } else {
-            if file_id == disk_contents[end] {
-                file_size += 1;
-                end -= 1;
-            } else {
-                let gap_start = find_gap(file_size, start, end + 1, &disk_contents);
-                if let Some(gap_start) = gap_start {
-                    for i in 0..file_size {
-                        disk_contents[gap_start + i] = disk_contents[end + 1 + i];
-                        disk_contents[end + 1 + i] = None;
-                    }
-                }
-                file_id = None;
-                file_size = 0;
-            }
-        }
-    }
-    generate_checksum(&disk_contents)
+fn part_2(input: &str) -> u128 {
+    compacted_checksum(input, &FirstFit)
 }
 
-fn generate_checksum(disk_contents: &[Option<u16>]) -> u64 {
-    let mut checksum = 0;
-    let mut count = 0;
-    for &block in disk_contents {
-        if let Some(block) = block {
-            checksum += count * u64::try_from(block).unwrap();
-        }
-        count += 1;
-    }
-    checksum
-}
-
-fn find_gap(
-    file_size: usize,
-    mut start: usize,
-    end: usize,
-    disk_contents: &[Option<u16>]
-) -> Option<usize> {
-    let mut gap_size = 0;
-    while start < end {
-        if let Some(_) = disk_contents[start] {
-            gap_size = 0;
-        } else {
-            gap_size += 1;
-            if gap_size >= file_size {
-                return Some(start - (gap_size - 1));
-            }
-        }
-        start += 1;
-    }
-    None
+fn compacted_checksum(input: &str, strategy: &dyn CompactionStrategy) -> u128 {
+    let mut disk = Disk::parse(input);
+    strategy.compact(&mut disk);
+    disk.checksum()
 }
 
-fn parse_input(input: &str) -> Vec<Option<u16>> {
-    let mut list = Vec::new();
-    for (i, c) in input.chars().enumerate() {
-        let size = c.to_digit(10).unwrap();
-        let id = if i % 2 == 0 {
-            Some(u16::try_from(i / 2).unwrap())
-        } else {
-            None
-        };
-        for _ in 0..size {
-            list.push(id);
-        }
+fn export_compact(input: &str, args: &[String]) -> String {
+    let disk = Disk::parse(input);
+    let strategies: Vec<&dyn CompactionStrategy> = if args.is_empty() {
+        STRATEGIES.to_vec()
+    } else {
+        args.iter()
+            .map(|name| {
+                *STRATEGIES.iter()
+                    .find(|strategy| strategy.name() == name)
+                    .unwrap_or_else(|| panic!("unknown strategy: {}", name))
+            })
+            .collect()
+    };
+    let mut output = String::new();
+    for strategy in strategies {
+        let report = compare(&disk, strategy);
+        output.push_str(&format!(
+            "{}: {} moves ({} blocks), gaps {} -> {}, free blocks between files {} -> {}, \
+                checksum {}\n{}\n",
+            report.strategy,
+            report.moves.extents,
+            report.moves.blocks,
+            report.before.gaps,
+            report.after.gaps,
+            report.before.free_blocks,
+            report.after.free_blocks,
+            report.checksum,
+            report.layout,
+        ));
     }
-    list
+    output
 }
 
 #[cfg(test)]
@@ -124,4 +85,96 @@ mod tests {
     fn part_2_sample() {
         assert_eq!(2858, part_2(INPUT));
     }
-}
\ No newline at end of file
+
+    // compacts block by block over the fully expanded disk, as a check on the extent-based version
+    fn compact_expanded(disk_map: &str, whole_files: bool) -> u128 {
+        let mut blocks: Vec<Option<u64>> = Vec::new();
+        for (i, c) in disk_map.chars().enumerate() {
+            let id = if i % 2 == 0 { Some(u64::try_from(i / 2).unwrap()) } else { None };
+            blocks.extend((0..c.to_digit(10).unwrap()).map(|_| id));
+        }
+        if whole_files {
+            let file_count = u64::try_from(disk_map.len().div_ceil(2)).unwrap();
+            for id in (0..file_count).rev() {
+                let Some(file_start) = blocks.iter().position(|&block| block == Some(id)) else {
+                    continue;
+                };
+                let file_len = blocks[file_start..].iter().take_while(|&&block| block == Some(id)).count();
+                let gap_start = (0..file_start)
+                    .find(|&start| blocks[start..start + file_len].iter().all(Option::is_none));
+                if let Some(gap_start) = gap_start {
+                    for i in 0..file_len {
+                        blocks[gap_start + i] = Some(id);
+                        blocks[file_start + i] = None;
+                    }
+                }
+            }
+        } else {
+            let mut start = 0;
+            let mut end = blocks.len() - 1;
+            while start < end {
+                match (blocks[start], blocks[end]) {
+                    (_, None) => end -= 1,
+                    (Some(_), _) => start += 1,
+                    (None, Some(_)) => blocks.swap(start, end),
+                }
+            }
+        }
+        blocks.iter().enumerate()
+            .filter_map(|(position, block)| block.map(|id| u128::from(id) * position as u128))
+            .sum()
+    }
+
+    #[test]
+    fn matches_expanded_compaction() {
+        let disk_map: String = (0..3_000)
+            .map(|i| char::from_digit((i * 7 % 10) as u32, 10).unwrap())
+            .collect();
+        for whole_files in [false, true] {
+            let mut disk = Disk::parse(&disk_map);
+            if whole_files {
+                FirstFit.compact(&mut disk);
+            } else {
+                BlockByBlock.compact(&mut disk);
+            }
+            assert_eq!(compact_expanded(&disk_map, whole_files), disk.checksum());
+        }
+    }
+
+    #[test]
+    fn more_files_than_u16() {
+        // 100,000 single-block files with no gaps, so each block's position is its file ID
+        let disk_map = "10".repeat(100_000);
+        let expected: u128 = (0..100_000_u128).map(|id| id * id).sum();
+        assert_eq!(expected, u128::from(part_1(&disk_map)));
+        assert_eq!(expected, u128::from(part_2(&disk_map)));
+    }
+
+    #[test]
+    fn export_compact_sample() {
+        let args: Vec<String> = ["first-fit", "best-fit", "worst-fit", "first-fit-anywhere", "stable-order"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        let output = export_compact(INPUT, &args);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!("first-fit: 4 moves (8 blocks), gaps 8 -> 5, free blocks between files 14 -> 12, \
+            checksum 2858", lines[0]);
+        assert_eq!("00992111777.44.333....5555.6666.....8888..", lines[1]);
+        assert!(lines[6].starts_with("first-fit-anywhere: 9 moves (24 blocks), gaps 8 -> 7"));
+        assert_eq!("..99200.777.44111.333.....5555.6666.8888..", lines[7]);
+        assert!(lines[8].starts_with("stable-order: 9 moves (26 blocks), gaps 8 -> 0"));
+        assert_eq!("0011123334455556666777888899..............", lines[9]);
+
+        // the sample has no choice of gaps to tell these apart, but this does
+        let layouts = |input| {
+            let names = [String::from("best-fit"), String::from("worst-fit")];
+            export_compact(input, &names).lines()
+                .skip(1)
+                .step_by(2)
+                .map(String::from)
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(vec!["01.....32.....", "0321.........."], layouts("1512131"));
+    }
+}
@@ -0,0 +1,239 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+
+pub type FileId = u64;
+
+/// A disk made up of runs of contiguous blocks (extents) belonging to files, with everything not
+/// covered by an extent being free space.
+#[derive(Clone)]
+pub struct Disk {
+    extents: Vec<Extent>,
+    size: u64,
+}
+
+#[derive(Copy, Clone)]
+pub struct Extent {
+    pub file_id: FileId,
+    pub start: u64,
+    pub len: u64,
+}
+
+#[derive(Copy, Clone)]
+pub struct Span {
+    pub start: u64,
+    pub len: u64,
+}
+
+/// Which of the free spans that a file fits in it gets moved to.
+#[derive(Copy, Clone)]
+pub enum Fit {
+    Leftmost,
+    Smallest,
+    Largest,
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct Moves {
+    pub extents: usize,
+    pub blocks: u64,
+}
+
+/// Free space that's stuck between file blocks, rather than all at the end of the disk.
+pub struct Fragmentation {
+    pub gaps: usize,
+    pub free_blocks: u64,
+}
+
+impl Disk {
+    pub fn parse(disk_map: &str) -> Self {
+        let mut extents = Vec::new();
+        let mut position = 0;
+        for (i, c) in disk_map.trim_end().chars().enumerate() {
+            let len = u64::from(c.to_digit(10).unwrap());
+            if i % 2 == 0 && len > 0 {
+                extents.push(Extent { file_id: u64::try_from(i / 2).unwrap(), start: position, len });
+            }
+            position += len;
+        }
+        Disk { extents, size: position }
+    }
+
+    /// Moves individual blocks from the end of the disk into the leftmost free block, until there
+    /// are no gaps left between file blocks.
+    pub fn compact_blocks(&mut self) -> Moves {
+        let mut moves = Moves::default();
+        let mut free_spans = self.free_spans();
+        let mut free_index = 0;
+        let mut moved = Vec::new();
+        while let Some(last) = self.extents.last_mut() {
+            let Some(free_span) = free_spans.get_mut(free_index)
+                .filter(|span| span.start < last.start) else {
+                break;
+            };
+            let moved_len = u64::min(free_span.len, last.len);
+            moved.push(Extent { file_id: last.file_id, start: free_span.start, len: moved_len });
+            moves.extents += 1;
+            moves.blocks += moved_len;
+            free_span.start += moved_len;
+            free_span.len -= moved_len;
+            if free_span.len == 0 {
+                free_index += 1;
+            }
+            last.len -= moved_len;
+            if last.len == 0 {
+                self.extents.pop();
+            }
+        }
+        self.extents.extend(moved);
+        self.extents.sort_unstable_by_key(|extent| extent.start);
+        moves
+    }
+
+    /// Moves each whole file, in decreasing file ID order, into a free span that's to its left
+    /// and big enough to hold it (if there is one), picking between those spans by `fit`.
+    pub fn compact_files(&mut self, fit: Fit) -> Moves {
+        let mut moves = Moves::default();
+        // free spans indexed by their size, each index giving the leftmost span first; moving a
+        // file only ever shrinks a span or frees space to the right of every file still to move,
+        // so spans never need merging
+        let free_spans = self.free_spans();
+        let max_len = free_spans.iter().map(|span| span.len).max().unwrap_or(0);
+        let mut spans_by_len: Vec<BinaryHeap<Reverse<u64>>> = (0..=max_len)
+            .map(|_| BinaryHeap::new())
+            .collect();
+        for span in free_spans {
+            spans_by_len[usize::try_from(span.len).unwrap()].push(Reverse(span.start));
+        }
+        self.extents.sort_unstable_by_key(|extent| Reverse(extent.file_id));
+        for extent in self.extents.iter_mut() {
+            // the leftmost span of each size that fits, as (start, size)
+            let mut fits = spans_by_len.iter()
+                .enumerate()
+                .skip(usize::try_from(extent.len).unwrap())
+                .filter_map(|(len, spans)| spans.peek().map(|&Reverse(start)| (start, len)))
+                .filter(|&(start, _)| start < extent.start);
+            let chosen_fit = match fit {
+                Fit::Leftmost => fits.min(),
+                Fit::Smallest => fits.next(),
+                Fit::Largest => fits.next_back(),
+            };
+            if let Some((start, len)) = chosen_fit {
+                spans_by_len[len].pop();
+                let remaining = len - usize::try_from(extent.len).unwrap();
+                if remaining > 0 {
+                    spans_by_len[remaining].push(Reverse(start + extent.len));
+                }
+                extent.start = start;
+                moves.extents += 1;
+                moves.blocks += extent.len;
+            }
+        }
+        self.extents.sort_unstable_by_key(|extent| extent.start);
+        moves
+    }
+
+    /// Moves each whole file, in decreasing file ID order, into the leftmost free span anywhere on
+    /// the disk that's big enough to hold it, even if that's to its right.
+    pub fn compact_files_anywhere(&mut self) -> Moves {
+        let mut moves = Moves::default();
+        // files can now move right, freeing up space that files still to move may want, so the
+        // free spans need merging back together as files leave them
+        let mut free_spans: BTreeMap<u64, u64> = self.free_spans().iter()
+            .map(|span| (span.start, span.len))
+            .collect();
+        self.extents.sort_unstable_by_key(|extent| Reverse(extent.file_id));
+        for extent in self.extents.iter_mut() {
+            let Some((&start, &len)) = free_spans.iter().find(|&(_, &len)| len >= extent.len) else {
+                continue;
+            };
+            free_spans.remove(&start);
+            if len > extent.len {
+                free_spans.insert(start + extent.len, len - extent.len);
+            }
+            let (mut freed_start, mut freed_len) = (extent.start, extent.len);
+            if let Some((&before_start, &before_len)) = free_spans.range(..freed_start).next_back()
+                .filter(|&(&before_start, &before_len)| before_start + before_len == freed_start) {
+                free_spans.remove(&before_start);
+                freed_start = before_start;
+                freed_len += before_len;
+            }
+            if let Some(after_len) = free_spans.remove(&(freed_start + freed_len)) {
+                freed_len += after_len;
+            }
+            free_spans.insert(freed_start, freed_len);
+            extent.start = start;
+            moves.extents += 1;
+            moves.blocks += extent.len;
+        }
+        self.extents.sort_unstable_by_key(|extent| extent.start);
+        moves
+    }
+
+    /// Slides every file left, keeping them in the order they're already in, until there are no
+    /// gaps left between them.
+    pub fn pack(&mut self) -> Moves {
+        let mut moves = Moves::default();
+        let mut position = 0;
+        for extent in self.extents.iter_mut() {
+            if extent.start != position {
+                extent.start = position;
+                moves.extents += 1;
+                moves.blocks += extent.len;
+            }
+            position += extent.len;
+        }
+        moves
+    }
+
+    pub fn fragmentation(&self) -> Fragmentation {
+        let used_end = self.extents.last().map_or(0, |extent| extent.start + extent.len);
+        let trapped: Vec<Span> = self.free_spans().into_iter()
+            .filter(|span| span.start < used_end)
+            .collect();
+        Fragmentation {
+            gaps: trapped.len(),
+            free_blocks: trapped.iter().map(|span| span.len).sum(),
+        }
+    }
+
+    /// Draws every block in the puzzle's notation, e.g. `00...111...2`, with file IDs that need
+    /// more than one digit shown in brackets.
+    pub fn layout(&self) -> String {
+        let mut layout = String::new();
+        let mut position = 0;
+        for extent in &self.extents {
+            layout.push_str(&".".repeat(usize::try_from(extent.start - position).unwrap()));
+            let block = if extent.file_id < 10 {
+                extent.file_id.to_string()
+            } else {
+                format!("({})", extent.file_id)
+            };
+            layout.push_str(&block.repeat(usize::try_from(extent.len).unwrap()));
+            position = extent.start + extent.len;
+        }
+        layout.push_str(&".".repeat(usize::try_from(self.size - position).unwrap()));
+        layout
+    }
+
+    pub fn checksum(&self) -> u128 {
+        self.extents.iter()
+            .map(|extent| {
+                // sum of file ID * position over each block in the extent
+                let (start, len) = (u128::from(extent.start), u128::from(extent.len));
+                u128::from(extent.file_id) * (start * len + len * (len - 1) / 2)
+            })
+            .sum()
+    }
+
+    pub fn free_spans(&self) -> Vec<Span> {
+        let mut free_spans = Vec::new();
+        let mut position = 0;
+        for extent in self.extents.iter().chain([&Extent { file_id: 0, start: self.size, len: 0 }]) {
+            if extent.start > position {
+                free_spans.push(Span { start: position, len: extent.start - position });
+            }
+            position = extent.start + extent.len;
+        }
+        free_spans
+    }
+}
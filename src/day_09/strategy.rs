@@ -0,0 +1,112 @@
+use crate::day_09::disk::{Disk, Fit, Fragmentation, Moves};
+
+/// A way of compacting a disk, for comparing how well each one does.
+pub trait CompactionStrategy {
+    fn name(&self) -> &'static str;
+
+    fn compact(&self, disk: &mut Disk) -> Moves;
+}
+
+/// Moves single blocks from the end of the disk into the leftmost gap (part 1).
+pub struct BlockByBlock;
+
+/// Moves whole files into the leftmost gap to their left that fits (part 2).
+pub struct FirstFit;
+
+/// Moves whole files into the smallest gap to their left that fits.
+pub struct BestFit;
+
+/// Moves whole files into the largest gap to their left that fits.
+pub struct WorstFit;
+
+/// Moves whole files into the leftmost gap that fits, even when that's to their right.
+pub struct FirstFitAnywhere;
+
+/// Slides files left without reordering them.
+pub struct StableOrder;
+
+impl CompactionStrategy for BlockByBlock {
+    fn name(&self) -> &'static str {
+        "blocks"
+    }
+
+    fn compact(&self, disk: &mut Disk) -> Moves {
+        disk.compact_blocks()
+    }
+}
+
+impl CompactionStrategy for FirstFit {
+    fn name(&self) -> &'static str {
+        "first-fit"
+    }
+
+    fn compact(&self, disk: &mut Disk) -> Moves {
+        disk.compact_files(Fit::Leftmost)
+    }
+}
+
+impl CompactionStrategy for BestFit {
+    fn name(&self) -> &'static str {
+        "best-fit"
+    }
+
+    fn compact(&self, disk: &mut Disk) -> Moves {
+        disk.compact_files(Fit::Smallest)
+    }
+}
+
+impl CompactionStrategy for WorstFit {
+    fn name(&self) -> &'static str {
+        "worst-fit"
+    }
+
+    fn compact(&self, disk: &mut Disk) -> Moves {
+        disk.compact_files(Fit::Largest)
+    }
+}
+
+impl CompactionStrategy for FirstFitAnywhere {
+    fn name(&self) -> &'static str {
+        "first-fit-anywhere"
+    }
+
+    fn compact(&self, disk: &mut Disk) -> Moves {
+        disk.compact_files_anywhere()
+    }
+}
+
+impl CompactionStrategy for StableOrder {
+    fn name(&self) -> &'static str {
+        "stable-order"
+    }
+
+    fn compact(&self, disk: &mut Disk) -> Moves {
+        disk.pack()
+    }
+}
+
+pub const STRATEGIES: [&dyn CompactionStrategy; 6] =
+    [&BlockByBlock, &FirstFit, &BestFit, &WorstFit, &FirstFitAnywhere, &StableOrder];
+
+pub struct CompactionReport {
+    pub strategy: &'static str,
+    pub moves: Moves,
+    pub before: Fragmentation,
+    pub after: Fragmentation,
+    pub checksum: u128,
+    pub layout: String,
+}
+
+/// Runs the strategy over a copy of the disk, leaving the original as it was.
+pub fn compare(disk: &Disk, strategy: &dyn CompactionStrategy) -> CompactionReport {
+    let mut compacted = disk.clone();
+    let moves = strategy.compact(&mut compacted);
+    CompactionReport {
+        strategy: strategy.name(),
+        moves,
+        before: disk.fragmentation(),
+        after: compacted.fragmentation(),
+        checksum: compacted.checksum(),
+        layout: compacted.layout(),
+    }
+}
@@ -1,5 +1,7 @@
-use std::collections::HashSet;
-use crate::common::{DaySpec, Point};
+mod trails;
+
+use crate::common::{DaySpec, Export, Point};
+use crate::day_10::trails::{Heightmap, StepRule, TrailRules};
 
 pub const DAY_TEN: DaySpec<u32, u32> = DaySpec {
     day_num: 10,
@@ -7,87 +9,99 @@ pub const DAY_TEN: DaySpec<u32, u32> = DaySpec {
     part_1,
     part_2_name: "trailhead ratings sum",
     part_2,
+    exports: &[
+        Export {
+            name: "trails",
+            description: "score and rating of every trailhead; optional args are a step rule \
+                (climb[:k] or descend[:k]), heights as start-end, and x,y to list that \
+                trailhead's trails",
+            run: export_trails,
+            needs_input: true,
+        },
+    ],
 };
 
 fn part_1(input: &str) -> u32 {
-    let topography = parse_input(input);
-    let mut sum = 0;
-    for row in 0..topography.len() {
-        for col in 0..topography[0].len() {
-            if topography[row][col] == 0 {
-                sum += calculate_trailhead_score_sum(&topography, row, col) as u32;
-            }
-        }
-    }
-    sum
+    let heightmap = Heightmap::parse(input);
+    let rules = TrailRules::PUZZLE;
+    let score_sum: usize = heightmap.trailheads(&rules).into_iter()
+        .map(|trailhead| heightmap.score(&rules, trailhead))
+        .sum();
+    u32::try_from(score_sum).unwrap()
 }
 
 fn part_2(input: &str) -> u32 {
-    let topography = parse_input(input);
-    let mut sum = 0;
-    for row in 0..topography.len() {
-        for col in 0..topography[0].len() {
-            if topography[row][col] == 0 {
-                sum += calculate_trailhead_rating_sum(&topography, row, col) as u32;
-            }
-        }
-    }
-    sum
+    let heightmap = Heightmap::parse(input);
+    let rules = TrailRules::PUZZLE;
+    u32::try_from(rating_sum(&heightmap, &rules)).unwrap()
 }
 
-fn calculate_trailhead_score_sum(topography: &[Vec<u8>], row: usize, col: usize) -> usize {
-    let mut peaks = HashSet::new();
-    calculate_trailheads(topography, row, col, &mut peaks);
-    peaks.len()
+fn rating_sum(heightmap: &Heightmap, rules: &TrailRules) -> u128 {
+    let counts = heightmap.trail_counts(rules);
+    heightmap.trailheads(rules).into_iter()
+        .map(|trailhead| counts[heightmap.index(trailhead)])
+        .sum()
 }
 
-fn calculate_trailhead_rating_sum(topography: &[Vec<u8>], row: usize, col: usize) -> usize {
-    calculate_trailheads(topography, row, col, &mut HashSet::new())
-}
-
-fn calculate_trailheads(
-    topography: &[Vec<u8>],
-    row: usize,
-    col: usize,
-    peaks: &mut HashSet<Point>
-) -> usize {
-    let height = topography[row][col];
-    if height == 9 {
-        peaks.insert(Point {
-            x: isize::try_from(col).unwrap(),
-            y: isize::try_from(row).unwrap(),
-        });
-        return 1;
-    }
-    let mut rating_sum = 0;
-    if row > 0 && topography[row - 1][col] == height + 1 {
-        rating_sum += calculate_trailheads(topography, row - 1, col, peaks);
+fn export_trails(input: &str, args: &[String]) -> String {
+    let heightmap = Heightmap::parse(input);
+    let mut rules = TrailRules::PUZZLE;
+    let mut heights = None;
+    let mut trailhead = None;
+    for arg in args {
+        if let Some((x, y)) = arg.split_once(',') {
+            trailhead = Some(Point { x: x.parse().unwrap(), y: y.parse().unwrap() });
+        } else if let Some((start, end)) = arg.split_once('-') {
+            heights = Some((start.parse().unwrap(), end.parse().unwrap()));
+        } else {
+            rules = parse_step_rule(arg);
+        }
     }
-    if row < topography.len() - 1 && topography[row + 1][col] == height + 1 {
-        rating_sum += calculate_trailheads(topography, row + 1, col, peaks);
+    if let Some((start, end)) = heights {
+        rules.start = start;
+        rules.end = end;
     }
-    if col > 0 && topography[row][col - 1] == height + 1 {
-        rating_sum += calculate_trailheads(topography, row, col - 1, peaks);
+    let mut output = String::new();
+    if let Some(trailhead) = trailhead {
+        for trail in heightmap.trails(&rules, trailhead) {
+            let positions: Vec<String> = trail.iter()
+                .map(|point| format!("{},{}", point.x, point.y))
+                .collect();
+            output.push_str(&positions.join(" -> "));
+            output.push('\n');
+        }
+        return output;
     }
-    if col < topography[0].len() - 1 && topography[row][col + 1] == height + 1 {
-        rating_sum += calculate_trailheads(topography, row, col + 1, peaks);
+    let counts = heightmap.trail_counts(&rules);
+    for trailhead in heightmap.trailheads(&rules) {
+        output.push_str(&format!(
+            "{},{}: score {}, rating {}\n",
+            trailhead.x,
+            trailhead.y,
+            heightmap.score(&rules, trailhead),
+            counts[heightmap.index(trailhead)],
+        ));
     }
-    rating_sum
+    output.push_str(&format!("total rating {}\n", rating_sum(&heightmap, &rules)));
+    output
 }
 
-fn parse_input(input: &str) -> Vec<Vec<u8>> {
-    input.lines()
-        .map(|line| {
-            line.chars()
-                .map(|c| c.to_digit(10).unwrap().try_into().unwrap())
-                .collect()
-        })
-        .collect()
+/// Parses `climb`, `climb:3`, `descend` or `descend:2`. Descending trails run from 9 down to 0
+/// unless told otherwise.
+fn parse_step_rule(spec: &str) -> TrailRules {
+    let (direction, max) = spec.split_once(':').unwrap_or((spec, "1"));
+    let max = max.parse().unwrap();
+    match direction {
+        "climb" => TrailRules { step: StepRule::Climb { max }, start: 0, end: 9 },
+        "descend" => TrailRules { step: StepRule::Descend { max }, start: 9, end: 0 },
+        _ => panic!("unknown step rule: {}", spec),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     const INPUT: &str = "89010123
 78121874
@@ -107,4 +121,33 @@ mod tests {
     fn part_2_sample() {
         assert_eq!(81, part_2(INPUT));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn trails_from_trailhead() {
+        // the trailhead at 2,0 has a score of 5 and a rating of 20
+        let output = export_trails(INPUT, &[String::from("2,0")]);
+        let trails: Vec<&str> = output.lines().collect();
+        assert_eq!(20, trails.len());
+        assert!(trails.iter().all(|trail| trail.starts_with("2,0 -> ") && trail.split(" -> ").count() == 10));
+        assert_eq!(5, trails.iter().map(|trail| trail.rsplit(" -> ").next().unwrap()).collect::<HashSet<_>>().len());
+    }
+
+    #[test]
+    fn configurable_rules() {
+        let heightmap = Heightmap::parse(INPUT);
+        // every descending trail is an ascending one walked backwards
+        assert_eq!(81, rating_sum(&heightmap, &parse_step_rule("descend")));
+        // allowing bigger climbs only adds trails
+        let steep = parse_step_rule("climb:2");
+        assert!(rating_sum(&heightmap, &steep) > 81);
+
+        let heightmap = Heightmap::parse("0123\n1234\n2345");
+        let rules = TrailRules { step: StepRule::Climb { max: 1 }, start: 0, end: 5 };
+        // monotone lattice paths through a 3x4 grid: 5 choose 2
+        assert_eq!(10, rating_sum(&heightmap, &rules));
+        let rules = TrailRules { step: StepRule::Climb { max: 3 }, start: 1, end: 4 };
+        // listing the trails agrees with counting them
+        assert_eq!(heightmap.trails(&rules, Point { x: 1, y: 0 }).len() as u128,
+            heightmap.trail_counts(&rules)[1]);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::common::Point;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+/// Which neighbouring heights a trail can step onto. Every step strictly climbs or strictly
+/// descends, so no trail can revisit a position.
+#[derive(Copy, Clone)]
+pub enum StepRule {
+    Climb { max: u8 },
+    Descend { max: u8 },
+}
+
+/// What counts as a trail: a path following `step` from a position at height `start` to one at
+/// height `end`.
+#[derive(Copy, Clone)]
+pub struct TrailRules {
+    pub step: StepRule,
+    pub start: u8,
+    pub end: u8,
+}
+
+impl TrailRules {
+    /// Trails as the puzzle defines them, climbing by exactly 1 from 0 to 9.
+    pub const PUZZLE: TrailRules = TrailRules {
+        step: StepRule::Climb { max: 1 },
+        start: 0,
+        end: 9,
+    };
+
+    fn allows(&self, from: u8, to: u8) -> bool {
+        match self.step {
+            StepRule::Climb { max } => to > from && to - from <= max,
+            StepRule::Descend { max } => to < from && from - to <= max,
+        }
+    }
+}
+
+pub struct Heightmap {
+    width: usize,
+    heights: Vec<u8>,
+}
+
+impl Heightmap {
+    pub fn parse(input: &str) -> Self {
+        let width = input.lines().next().map_or(0, str::len);
+        let heights = input.lines()
+            .flat_map(|line| line.chars())
+            .map(|c| u8::try_from(c.to_digit(10).unwrap()).unwrap())
+            .collect();
+        Heightmap { width, heights }
+    }
+
+    pub fn trailheads(&self, rules: &TrailRules) -> Vec<Point> {
+        (0..self.heights.len())
+            .filter(|&index| self.heights[index] == rules.start)
+            .map(|index| self.point(index))
+            .collect()
+    }
+
+    /// The number of distinct trail ends reachable from the trailhead.
+    pub fn score(&self, rules: &TrailRules, trailhead: Point) -> usize {
+        let mut seen = HashSet::from([self.index(trailhead)]);
+        let mut pending = vec![self.index(trailhead)];
+        let mut ends = 0;
+        while let Some(index) = pending.pop() {
+            if self.heights[index] == rules.end {
+                ends += 1;
+                continue;
+            }
+            for next in self.steps(rules, index) {
+                if seen.insert(next) {
+                    pending.push(next);
+                }
+            }
+        }
+        ends
+    }
+
+    /// For every position, the number of distinct trails from there to an end. Each count only
+    /// depends on the counts of the positions a step away, so positions are filled in working
+    /// back from the ends.
+    pub fn trail_counts(&self, rules: &TrailRules) -> Vec<u128> {
+        let mut order: Vec<usize> = (0..self.heights.len()).collect();
+        match rules.step {
+            StepRule::Climb { .. } => order.sort_by_key(|&index| Reverse(self.heights[index])),
+            StepRule::Descend { .. } => order.sort_by_key(|&index| self.heights[index]),
+        }
+        let mut counts = vec![0; self.heights.len()];
+        for index in order {
+            counts[index] = if self.heights[index] == rules.end {
+                1
+            } else {
+                self.steps(rules, index).map(|next| counts[next]).sum()
+            };
+        }
+        counts
+    }
+
+    /// Every trail from the trailhead, as the positions along it.
+    pub fn trails(&self, rules: &TrailRules, trailhead: Point) -> Vec<Vec<Point>> {
+        // only following steps that lead to at least one end avoids exploring dead ends
+        let counts = self.trail_counts(rules);
+        let mut trails = Vec::new();
+        let mut pending = vec![vec![self.index(trailhead)]];
+        while let Some(trail) = pending.pop() {
+            let &last = trail.last().unwrap();
+            if counts[last] == 0 {
+                continue;
+            }
+            if self.heights[last] == rules.end {
+                trails.push(trail.iter().map(|&index| self.point(index)).collect());
+                continue;
+            }
+            // pushed in reverse so trails come out trying north, east, south, then west first
+            let nexts: Vec<usize> = self.steps(rules, last).collect();
+            for &next in nexts.iter().rev() {
+                let mut extended = trail.clone();
+                extended.push(next);
+                pending.push(extended);
+            }
+        }
+        trails
+    }
+
+    fn steps(&self, rules: &TrailRules, index: usize) -> impl Iterator<Item = usize> {
+        let point = self.point(index);
+        let rules = *rules;
+        [(0, -1), (1, 0), (0, 1), (-1, 0)].into_iter()
+            .map(move |(dx, dy)| Point { x: point.x + dx, y: point.y + dy })
+            .filter(|next| next.in_bounds(self.width, self.heights.len() / self.width))
+            .map(|next| self.index(next))
+            .filter(move |&next| rules.allows(self.heights[index], self.heights[next]))
+    }
+
+    fn point(&self, index: usize) -> Point {
+        Point {
+            x: isize::try_from(index % self.width).unwrap(),
+            y: isize::try_from(index / self.width).unwrap(),
+        }
+    }
+
+    pub fn index(&self, point: Point) -> usize {
+        usize::try_from(point.y).unwrap() * self.width + usize::try_from(point.x).unwrap()
+    }
+}
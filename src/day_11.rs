@@ -1,68 +1,194 @@
-use std::collections::HashMap;
-use crate::common::DaySpec;
+mod blink;
+mod closed_set;
+
+use crate::common::{DaySpec, Export};
+use crate::day_11::blink::{Blinker, RuleSet, Stone, distribution};
+use crate::day_11::closed_set::{ClosedSet, Exact, Modulo};
+use num::BigUint;
 
 pub const DAY_ELEVEN: DaySpec<u64, u64> = DaySpec {
     day_num: 11,
     part_1_name: "stones after 25 blinks",
     part_1,
     part_2_name: "stones after 75 blinks",
-    part_2
+    part_2,
+    exports: &[
+        Export {
+            name: "stones",
+            description: "stone count and the most common stone values after some blinks; args \
+                are the number of blinks (default 75) and how many values to list (default 10)",
+            run: export_stones,
+            needs_input: true,
+        },
+        Export {
+            name: "closed",
+            description: "the closed set of stone values the stones settle into, and the stone \
+                count after any number of blinks via a recurrence on its counts; args are the \
+                number of blinks and a modulus (default 1000000007) or \"exact\"",
+            run: export_closed,
+            needs_input: true,
+        },
+    ],
 };
 
 fn part_1(input: &str) -> u64 {
-    let stone_counts = parse_input(input);
-    calculate_stones(stone_counts, 25)
+    u64::try_from(stone_count(&parse_input(input), 25)).unwrap()
 }
 
 fn part_2(input: &str) -> u64 {
-    let stone_counts = parse_input(input);
-    calculate_stones(stone_counts, 75)
+    u64::try_from(stone_count(&parse_input(input), 75)).unwrap()
 }
 
-fn calculate_stones(mut stone_counts: HashMap<u64, u64>, blinks: u8) -> u64 {
-    for _ in 0..blinks {
-        let mut new_stone_counts = HashMap::new();
-        for (&stone, &count) in stone_counts.iter() {
-            if stone == 0 {
-                update_stone(&mut new_stone_counts, 1, count);
-            } else if stone.to_string().len() % 2 == 0 {
-                let stone_str = stone.to_string();
-                let left_new_stone_str = &stone_str[0..stone_str.len() / 2];
-                let right_new_stone_str = &stone_str[(stone_str.len() / 2)..];
-                update_stone(&mut new_stone_counts, left_new_stone_str.parse().unwrap(), count);
-                update_stone(&mut new_stone_counts, right_new_stone_str.parse().unwrap(), count);
-            } else {
-                update_stone(&mut new_stone_counts, stone * 2024, count);
-            }
-        }
-        stone_counts = new_stone_counts;
-        let mut my_vec = Vec::new();
-        for val in &stone_counts {
-            my_vec.push(val.1.clone());
-        }
-        my_vec.sort();
+fn stone_count(stones: &[Stone], blinks: u32) -> BigUint {
+    Blinker::new(RuleSet::puzzle()).blink_times(stones, blinks).into_values().sum()
+}
+
+fn export_stones(input: &str, args: &[String]) -> String {
+    let blinks = args.first().map_or(75, |arg| arg.parse().unwrap());
+    let limit = args.get(1).map_or(10, |arg| arg.parse().unwrap());
+    let mut blinker = Blinker::new(RuleSet::puzzle());
+    let counts = blinker.blink_times(&parse_input(input), blinks);
+    let total: BigUint = counts.values().sum();
+    let distribution = distribution(counts);
+    let rule_names: Vec<&str> = blinker.rules().rules().iter()
+        .map(|rule| rule.name)
+        .collect();
+    let mut output = format!(
+        "rules: {}\n{} stones after {} blinks, with {} distinct values\n",
+        rule_names.join(", "),
+        total,
+        blinks,
+        distribution.len(),
+    );
+    for (stone, count) in distribution.iter().take(limit) {
+        output.push_str(&format!("{}: {}\n", stone, count));
     }
-    stone_counts.values().sum()
+    output
 }
 
-fn update_stone(stone_counts: &mut HashMap<u64, u64>, stone: u64, count: u64) {
-    stone_counts.entry(stone)
-        .and_modify(|old| *old += count)
-        .or_insert(count);
+fn export_closed(input: &str, args: &[String]) -> String {
+    let stones = parse_input(input);
+    let blinks: u64 = args.first().expect("number of blinks should be given").parse().unwrap();
+    let mut blinker = Blinker::new(RuleSet::puzzle());
+    let closed_set = ClosedSet::find(&mut blinker, &stones);
+    let (count, counted_in) = match args.get(1).map(String::as_str) {
+        Some("exact") => {
+            let count = closed_set.stone_count(&Exact, &mut blinker, &stones, blinks);
+            (count.to_string(), String::from("exactly"))
+        }
+        modulus => {
+            let modulus = modulus.map_or(1_000_000_007, |modulus| modulus.parse().unwrap());
+            let count = closed_set.stone_count(&Modulo(modulus), &mut blinker, &stones, blinks);
+            (count.to_string(), format!("modulo {}", modulus))
+        }
+    };
+    format!(
+        "closed set of {} values, reached after {} blinks\n{} stones after {} blinks, {}\n",
+        closed_set.stones.len(),
+        closed_set.warm_up,
+        count,
+        blinks,
+        counted_in,
+    )
 }
 
-fn parse_input(input: &str) -> HashMap<u64, u64> {
-    input.split(" ")
-        .map(|stone| (stone.parse().unwrap(), 1))
+fn parse_input(input: &str) -> Vec<Stone> {
+    input.split_whitespace()
+        .map(|stone| stone.parse().unwrap())
         .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::{Duration, Instant};
 
     #[test]
     fn part_1_sample() {
         assert_eq!(55312, part_1("125 17"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn part_2_sample() {
+        assert_eq!(65601038650482, part_2("125 17"));
+    }
+
+    #[test]
+    fn many_blinks() {
+        // far past where the count fits in a u64, which goes at around 130 blinks
+        let count = stone_count(&[125, 17], 2_000);
+        assert!(count.bits() > 1_000);
+        let output = export_stones("125 17", &[String::from("6"), String::from("3")]);
+        // the puzzle's example after 6 blinks: 2097446912 14168 4048 2 0 2 4 40 48 2024 40 48
+        // 80 96 2 8 6 7 6 0 3 2
+        assert_eq!("rules: 0 becomes 1, even number of digits splits in half, multiplied by 2024\n\
+            22 stones after 6 blinks, with 15 distinct values\n2: 4\n0: 2\n6: 2\n", output);
+    }
+
+    #[test]
+    fn custom_rules() {
+        use crate::day_11::blink::{Rule, SPLIT_EVEN_DIGITS};
+        // stones that are already even halve, the rest split or double
+        const HALVE_EVEN: Rule = Rule { name: "even halves", applies: |stone| stone.is_multiple_of(2), transform: |stone| vec![stone / 2] };
+        const DOUBLE: Rule = Rule { name: "doubles", applies: |_| true, transform: |stone| vec![stone * 2] };
+        let mut blinker = Blinker::new(RuleSet::new(vec![HALVE_EVEN, SPLIT_EVEN_DIGITS, DOUBLE]));
+        // 8 halves down to 1, while 13 splits into 1 and 3, which double and then halve back
+        assert_eq!(&[1, 3], blinker.expand(13));
+        let counts = blinker.blink_times(&[8, 13], 3);
+        let mut stones: Vec<(Stone, u64)> = counts.iter()
+            .map(|(&stone, count)| (stone, u64::try_from(count).unwrap()))
+            .collect();
+        stones.sort_unstable();
+        assert_eq!(vec![(1, 2), (3, 1)], stones);
+    }
+
+    #[test]
+    fn closed_set_counts() {
+        let stones = [125, 17];
+        let mut blinker = Blinker::new(RuleSet::puzzle());
+        let closed_set = ClosedSet::find(&mut blinker, &stones);
+        assert!(closed_set.stones.contains(&0) && !closed_set.stones.contains(&125));
+        // before, during and well after the warm up
+        for blinks in [6, closed_set.warm_up, 75, 300] {
+            let expected = stone_count(&stones, u32::try_from(blinks).unwrap());
+            assert_eq!(expected, closed_set.stone_count(&Exact, &mut blinker, &stones, blinks));
+            for modulus in [1_000_000_007, 1 << 32] {
+                let expected = u64::try_from(&expected % modulus).unwrap();
+                assert_eq!(expected, closed_set.stone_count(&Modulo(modulus), &mut blinker, &stones, blinks));
+            }
+        }
+        // a modulus that isn't prime takes the transition matrix's powers, and a multiple of
+        // the prime gives the same count modulo the prime
+        let blinks = 1_000_000_000_000_000_000;
+        let started = Instant::now();
+        let count = closed_set.stone_count(&Modulo(4 * 1_000_000_007), &mut blinker, &stones, blinks);
+        assert_eq!(closed_set.stone_count(&Modulo(1_000_000_007), &mut blinker, &stones, blinks), count % 1_000_000_007);
+        let count = closed_set.stone_count(&Modulo(1 << 32), &mut blinker, &stones, blinks);
+        assert_eq!(count % 4, closed_set.stone_count(&Modulo(4), &mut blinker, &stones, blinks));
+        // as do exact counts, as long as they fit in memory
+        let exact = closed_set.stone_count(&Exact, &mut blinker, &stones, 5000);
+        let count = closed_set.stone_count(&Modulo(1_000_000_007), &mut blinker, &stones, 5000);
+        assert_eq!(BigUint::from(count), exact % 1_000_000_007_u64);
+        assert!(started.elapsed() < Duration::from_secs(60), "took {:?}", started.elapsed());
+        let output = export_closed("125 17", &[String::from("1000000000000000000")]);
+        assert!(output.lines().nth(1).unwrap().ends_with("stones after 1000000000000000000 blinks, modulo 1000000007"));
+    }
+
+    #[test]
+    fn closed_set_of_realistic_size() {
+        let stones = [0, 7, 6618216, 26481, 885, 42, 202642, 8791];
+        let mut blinker = Blinker::new(RuleSet::puzzle());
+        let started = Instant::now();
+        let closed_set = ClosedSet::find(&mut blinker, &stones);
+        assert_eq!((3811, 83), (closed_set.stones.len(), closed_set.warm_up));
+        let count = closed_set.stone_count(&Modulo(1_000_000_007), &mut blinker, &stones, 1_000_000_000_000_000_000);
+        assert_eq!(648364691, count);
+        assert!(started.elapsed() < Duration::from_secs(60), "took {:?}", started.elapsed());
+        // past where the count is extrapolated, which needs a prime modulus, so a multiple of
+        // the prime blinks all the way instead, as that's quicker than the matrix's powers here
+        let blinks = 10_000;
+        let extrapolated = closed_set.stone_count(&Modulo(1_000_000_007), &mut blinker, &stones, blinks);
+        let blinked = closed_set.stone_count(&Modulo(1_000_000_007 * 998_244_353), &mut blinker, &stones, blinks);
+        assert_eq!(blinked % 1_000_000_007, extrapolated);
+    }
+}
@@ -0,0 +1,123 @@
+use num::BigUint;
+use std::collections::HashMap;
+
+pub type Stone = u64;
+
+/// A rule for what a stone turns into when blinking. Rules are tried in order, with the first
+/// one that applies being used.
+#[derive(Copy, Clone)]
+pub struct Rule {
+    pub name: &'static str,
+    pub applies: fn(Stone) -> bool,
+    pub transform: fn(Stone) -> Vec<Stone>,
+}
+
+pub const ZERO_TO_ONE: Rule = Rule {
+    name: "0 becomes 1",
+    applies: |stone| stone == 0,
+    transform: |_| vec![1],
+};
+
+pub const SPLIT_EVEN_DIGITS: Rule = Rule {
+    name: "even number of digits splits in half",
+    applies: |stone| digit_count(stone).is_multiple_of(2),
+    transform: |stone| {
+        let (left, right) = split_digits(stone);
+        vec![left, right]
+    },
+};
+
+pub const MULTIPLY_2024: Rule = Rule {
+    name: "multiplied by 2024",
+    applies: |_| true,
+    transform: |stone| {
+        vec![stone.checked_mul(2024).unwrap_or_else(|| panic!("stone {} overflowed", stone))]
+    },
+};
+
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        RuleSet { rules }
+    }
+
+    pub fn puzzle() -> Self {
+        RuleSet::new(vec![ZERO_TO_ONE, SPLIT_EVEN_DIGITS, MULTIPLY_2024])
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn apply(&self, stone: Stone) -> Vec<Stone> {
+        let rule = self.rules.iter()
+            .find(|rule| (rule.applies)(stone))
+            .unwrap_or_else(|| panic!("no rule applies to stone {}", stone));
+        (rule.transform)(stone)
+    }
+}
+
+/// Blinks whole lines of stones by counting how many stones have each value, since stones with
+/// the same value always turn into the same stones. Each value's expansion is only worked out
+/// once, however many blinks it turns up in.
+pub struct Blinker {
+    rules: RuleSet,
+    expansions: HashMap<Stone, Vec<Stone>>,
+}
+
+impl Blinker {
+    pub fn new(rules: RuleSet) -> Self {
+        Blinker { rules, expansions: HashMap::new() }
+    }
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    pub fn expand(&mut self, stone: Stone) -> &[Stone] {
+        self.expansions.entry(stone).or_insert_with(|| self.rules.apply(stone))
+    }
+
+    pub fn blink(&mut self, stones: &HashMap<Stone, BigUint>) -> HashMap<Stone, BigUint> {
+        let mut blinked: HashMap<Stone, BigUint> = HashMap::new();
+        for (&stone, count) in stones {
+            for &new_stone in self.expand(stone) {
+                *blinked.entry(new_stone).or_default() += count;
+            }
+        }
+        blinked
+    }
+
+    /// The count of each distinct stone value after the given number of blinks.
+    pub fn blink_times(&mut self, stones: &[Stone], blinks: u32) -> HashMap<Stone, BigUint> {
+        let mut counts: HashMap<Stone, BigUint> = HashMap::new();
+        for &stone in stones {
+            *counts.entry(stone).or_default() += 1_u32;
+        }
+        for _ in 0..blinks {
+            counts = self.blink(&counts);
+        }
+        counts
+    }
+}
+
+/// Stone values with how many of each there are, most common first.
+pub fn distribution(counts: HashMap<Stone, BigUint>) -> Vec<(Stone, BigUint)> {
+    let mut distribution: Vec<(Stone, BigUint)> = counts.into_iter().collect();
+    distribution.sort_unstable_by(|(stone, count), (other_stone, other_count)| {
+        other_count.cmp(count).then(stone.cmp(other_stone))
+    });
+    distribution
+}
+
+fn digit_count(stone: Stone) -> u32 {
+    stone.checked_ilog10().unwrap_or(0) + 1
+}
+
+fn split_digits(stone: Stone) -> (Stone, Stone) {
+    let divisor = 10_u64.pow(digit_count(stone) / 2);
+    (stone / divisor, stone % divisor)
+}
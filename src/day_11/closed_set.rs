@@ -0,0 +1,450 @@
+use crate::day_11::blink::{Blinker, Stone};
+use num::BigUint;
+use std::collections::HashMap;
+
+/// The numbers that stone counts are worked out in.
+pub trait Ring {
+    type Elem: Clone;
+
+    fn zero(&self) -> Self::Elem;
+
+    fn one(&self) -> Self::Elem;
+
+    fn is_zero(&self, elem: &Self::Elem) -> bool;
+
+    fn add(&self, left: &Self::Elem, right: &Self::Elem) -> Self::Elem;
+
+    fn mul(&self, left: &Self::Elem, right: &Self::Elem) -> Self::Elem;
+
+    /// Term `n` of a sequence that follows a linear recurrence no longer than half of `terms`,
+    /// which are its first terms, if it can be worked out without going through every term in
+    /// between.
+    fn extrapolate(&self, _terms: &[Self::Elem], _n: u64) -> Option<Self::Elem> {
+        None
+    }
+}
+
+/// Integers modulo some number. Only a prime modulus lets counts be extrapolated, so others
+/// have to raise the transition matrix to a power instead.
+pub struct Modulo(pub u64);
+
+/// Exact counts, for when the number of blinks is small enough for them to fit in memory.
+pub struct Exact;
+
+impl Modulo {
+    fn sub(&self, left: u64, right: u64) -> u64 {
+        self.add(&left, &(self.0 - right % self.0))
+    }
+
+    fn inverse(&self, elem: u64) -> u64 {
+        pow_mod(elem, self.0 - 2, self.0)
+    }
+
+    /// The shortest recurrence `terms[i] = recurrence[0] * terms[i - 1] + recurrence[1] *
+    /// terms[i - 2] + ...` that the terms follow, found with the Berlekamp-Massey algorithm.
+    fn recurrence(&self, terms: &[u64]) -> Vec<u64> {
+        let mut recurrence: Vec<u64> = Vec::new();
+        // the recurrence before it last had to get longer, where that was, and how far off it
+        // was there
+        let mut previous: Vec<u64> = Vec::new();
+        let mut previous_at = 0;
+        let mut previous_error = 0;
+        for (i, &term) in terms.iter().enumerate() {
+            let predicted = recurrence.iter()
+                .enumerate()
+                .fold(0, |total, (j, &coefficient)| self.add(&total, &self.mul(&coefficient, &terms[i - 1 - j])));
+            let error = self.sub(term, predicted);
+            if error == 0 {
+                continue;
+            }
+            if recurrence.is_empty() {
+                recurrence = vec![0; i + 1];
+                previous_at = i;
+                previous_error = error;
+                continue;
+            }
+            let scale = self.mul(&error, &self.inverse(previous_error));
+            let mut corrected = vec![0; i - previous_at - 1];
+            corrected.push(scale);
+            corrected.extend(previous.iter().map(|&coefficient| self.sub(0, self.mul(&coefficient, &scale))));
+            if corrected.len() < recurrence.len() {
+                corrected.resize(recurrence.len(), 0);
+            }
+            for (corrected, coefficient) in corrected.iter_mut().zip(&recurrence) {
+                *corrected = self.add(corrected, coefficient);
+            }
+            if i - previous_at + previous.len() >= recurrence.len() {
+                previous = std::mem::replace(&mut recurrence, corrected);
+                previous_at = i;
+                previous_error = error;
+            } else {
+                recurrence = corrected;
+            }
+        }
+        recurrence
+    }
+
+    /// The product of two polynomials, given lowest power first, reduced by using the
+    /// recurrence to rewrite `x^d` in terms of lower powers, where `d` is its length.
+    fn mul_reduced(&self, left: &[u64], right: &[u64], recurrence: &[u64]) -> Vec<u64> {
+        let length = recurrence.len();
+        let mut product = vec![0_u128; 2 * length - 1];
+        for (i, &left) in left.iter().enumerate() {
+            if left != 0 {
+                for (j, &right) in right.iter().enumerate() {
+                    self.mul_add(&mut product[i + j], left, right);
+                }
+            }
+        }
+        for power in (length..2 * length - 1).rev() {
+            let coefficient = self.reduce(product[power]);
+            if coefficient != 0 {
+                for (j, &step) in recurrence.iter().enumerate() {
+                    self.mul_add(&mut product[power - 1 - j], coefficient, step);
+                }
+            }
+        }
+        product[..length].iter().map(|&coefficient| self.reduce(coefficient)).collect()
+    }
+
+    /// Adds a product to a running total that's only reduced when it gets near overflowing,
+    /// which saves most of the divisions.
+    fn mul_add(&self, total: &mut u128, left: u64, right: u64) {
+        let mut product = u128::from(left) * u128::from(right);
+        if self.0 > 1 << 63 {
+            product %= u128::from(self.0);
+        }
+        *total += product;
+        if *total >= 1 << 127 {
+            *total %= u128::from(self.0);
+        }
+    }
+
+    fn reduce(&self, total: u128) -> u64 {
+        u64::try_from(total % u128::from(self.0)).unwrap()
+    }
+}
+
+impl Ring for Modulo {
+    type Elem = u64;
+
+    fn zero(&self) -> u64 {
+        0
+    }
+
+    fn one(&self) -> u64 {
+        1 % self.0
+    }
+
+    fn is_zero(&self, elem: &u64) -> bool {
+        *elem == 0
+    }
+
+    fn add(&self, left: &u64, right: &u64) -> u64 {
+        u64::try_from((u128::from(*left) + u128::from(*right)) % u128::from(self.0)).unwrap()
+    }
+
+    fn mul(&self, left: &u64, right: &u64) -> u64 {
+        mul_mod(*left, *right, self.0)
+    }
+
+    /// Finds the recurrence, then works out `x^n` modulo its characteristic polynomial by
+    /// repeated squaring, which gives term `n` as a combination of the first terms. That takes
+    /// time quadratic in the length of the recurrence, and logarithmic in `n`.
+    fn extrapolate(&self, terms: &[u64], n: u64) -> Option<u64> {
+        if !is_prime(self.0) {
+            return None;
+        }
+        let recurrence = self.recurrence(terms);
+        if recurrence.is_empty() {
+            return Some(0);
+        }
+        let mut power = vec![0; recurrence.len()];
+        power[0] = self.one();
+        for bit in (0..u64::BITS - n.leading_zeros()).rev() {
+            power = self.mul_reduced(&power, &power, &recurrence);
+            if n >> bit & 1 == 1 {
+                // multiplying by x moves every coefficient up one, and the top one round
+                let top = power.pop().unwrap();
+                power.insert(0, 0);
+                for (coefficient, &step) in power.iter_mut().rev().zip(&recurrence) {
+                    *coefficient = self.add(coefficient, &self.mul(&top, &step));
+                }
+            }
+        }
+        let term = power.iter()
+            .zip(terms)
+            .fold(0, |total, (&coefficient, &term)| self.add(&total, &self.mul(&coefficient, &term)));
+        Some(term)
+    }
+}
+
+impl Ring for Exact {
+    type Elem = BigUint;
+
+    fn zero(&self) -> BigUint {
+        BigUint::ZERO
+    }
+
+    fn one(&self) -> BigUint {
+        BigUint::from(1_u32)
+    }
+
+    fn is_zero(&self, elem: &BigUint) -> bool {
+        *elem == BigUint::ZERO
+    }
+
+    fn add(&self, left: &BigUint, right: &BigUint) -> BigUint {
+        left + right
+    }
+
+    fn mul(&self, left: &BigUint, right: &BigUint) -> BigUint {
+        left * right
+    }
+}
+
+fn mul_mod(left: u64, right: u64, modulus: u64) -> u64 {
+    u64::try_from(u128::from(left) * u128::from(right) % u128::from(modulus)).unwrap()
+}
+
+fn pow_mod(mut base: u64, mut exponent: u64, modulus: u64) -> u64 {
+    let mut result = 1 % modulus;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, modulus);
+        }
+        base = mul_mod(base, base, modulus);
+        exponent >>= 1;
+    }
+    result
+}
+
+/// Whether `n` is prime, by the Miller-Rabin test with the witnesses that are enough to decide
+/// for every 64 bit number.
+fn is_prime(n: u64) -> bool {
+    const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    if let Some(&witness) = WITNESSES.iter().find(|&&witness| n.is_multiple_of(witness)) {
+        return n == witness;
+    }
+    let twos = (n - 1).trailing_zeros();
+    let odd = (n - 1) >> twos;
+    WITNESSES.iter().all(|&witness| {
+        let mut x = pow_mod(witness, odd, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        (1..twos).any(|_| {
+            x = mul_mod(x, x, n);
+            x == n - 1
+        })
+    })
+}
+
+/// A square matrix, stored row by row.
+pub struct Matrix<E> {
+    size: usize,
+    entries: Vec<E>,
+}
+
+impl<E: Clone> Matrix<E> {
+    fn square<R: Ring<Elem = E>>(&self, ring: &R) -> Matrix<E> {
+        let size = self.size;
+        let mut entries = vec![ring.zero(); size * size];
+        for row in 0..size {
+            for mid in 0..size {
+                let left = &self.entries[row * size + mid];
+                // transition matrices are mostly zeros, at least for the first few squarings
+                if ring.is_zero(left) {
+                    continue;
+                }
+                for col in 0..size {
+                    let right = &self.entries[mid * size + col];
+                    if !ring.is_zero(right) {
+                        let product = ring.mul(left, right);
+                        entries[row * size + col] = ring.add(&entries[row * size + col], &product);
+                    }
+                }
+            }
+        }
+        Matrix { size, entries }
+    }
+
+    /// The vector, as a row, multiplied by the matrix.
+    fn apply<R: Ring<Elem = E>>(&self, ring: &R, vector: &[E]) -> Vec<E> {
+        let mut applied = vec![ring.zero(); self.size];
+        for (row, value) in vector.iter().enumerate() {
+            if ring.is_zero(value) {
+                continue;
+            }
+            for (col, applied_value) in applied.iter_mut().enumerate() {
+                let entry = &self.entries[row * self.size + col];
+                if !ring.is_zero(entry) {
+                    *applied_value = ring.add(applied_value, &ring.mul(value, entry));
+                }
+            }
+        }
+        applied
+    }
+}
+
+/// The stone values that keep turning up however many times the stones are blinked, which
+/// every stone is in from `warm_up` blinks on. Once every stone is in the set, blinking is
+/// multiplying the counts of each value by a fixed transition matrix, which has at most two
+/// stones in each row.
+pub struct ClosedSet {
+    pub stones: Vec<Stone>,
+    pub warm_up: u64,
+    indexes: HashMap<Stone, usize>,
+}
+
+impl ClosedSet {
+    /// Blinks the set of values until it's one it has been before, which `warm_up` is the
+    /// number of blinks to. From then on the stones only ever have the values in the sets
+    /// between it and when it came round again.
+    pub fn find(blinker: &mut Blinker, start: &[Stone]) -> Self {
+        let mut values: Vec<Stone> = start.to_vec();
+        values.sort_unstable();
+        values.dedup();
+        let mut seen: HashMap<Vec<Stone>, usize> = HashMap::new();
+        let mut sets = Vec::new();
+        let warm_up = loop {
+            if let Some(&first) = seen.get(&values) {
+                break first;
+            }
+            seen.insert(values.clone(), sets.len());
+            let mut next: Vec<Stone> = values.iter()
+                .flat_map(|&stone| blinker.expand(stone).to_vec())
+                .collect();
+            next.sort_unstable();
+            next.dedup();
+            sets.push(std::mem::replace(&mut values, next));
+        };
+        let mut stones: Vec<Stone> = sets[warm_up..].concat();
+        stones.sort_unstable();
+        stones.dedup();
+        let indexes = stones.iter()
+            .enumerate()
+            .map(|(index, &stone)| (stone, index))
+            .collect();
+        ClosedSet { stones, warm_up: u64::try_from(warm_up).unwrap(), indexes }
+    }
+
+    /// The indexes of the stones that a stone of each value turns into.
+    pub fn transitions(&self, blinker: &mut Blinker) -> Vec<Vec<usize>> {
+        self.stones.iter()
+            .map(|&stone| blinker.expand(stone).iter().map(|new_stone| self.indexes[new_stone]).collect())
+            .collect()
+    }
+
+    /// The matrix giving how many stones of each value (by column) a stone of each value (by
+    /// row) turns into.
+    fn matrix<R: Ring>(&self, ring: &R, transitions: &[Vec<usize>]) -> Matrix<R::Elem> {
+        let size = self.stones.len();
+        let mut entries = vec![ring.zero(); size * size];
+        for (row, new_stones) in transitions.iter().enumerate() {
+            for &new_stone in new_stones {
+                let index = row * size + new_stone;
+                entries[index] = ring.add(&entries[index], &ring.one());
+            }
+        }
+        Matrix { size, entries }
+    }
+
+    /// The number of stones after the given number of blinks. This blinks directly through
+    /// the warm up, and then the counts of each value for twice as many blinks again as there
+    /// are values, since the totals from the warm up on follow a linear recurrence no longer
+    /// than that. The ring can then extrapolate from those totals. Failing that, the counts
+    /// carry on blinking if there aren't many blinks left, or else are multiplied by the
+    /// transition matrix raised to the power of the rest by repeated squaring, which takes time
+    /// cubic in the size of the set, but only logarithmic in the number of blinks.
+    pub fn stone_count<R: Ring>(
+        &self,
+        ring: &R,
+        blinker: &mut Blinker,
+        start: &[Stone],
+        blinks: u64,
+    ) -> R::Elem {
+        let mut counts: HashMap<Stone, R::Elem> = HashMap::new();
+        for &stone in start {
+            add_count(ring, &mut counts, stone, &ring.one());
+        }
+        for _ in 0..blinks.min(self.warm_up) {
+            let mut blinked = HashMap::new();
+            for (stone, count) in &counts {
+                for &new_stone in blinker.expand(*stone) {
+                    add_count(ring, &mut blinked, new_stone, count);
+                }
+            }
+            counts = blinked;
+        }
+        if blinks <= self.warm_up {
+            return total(ring, counts.values());
+        }
+        let mut vector = vec![ring.zero(); self.stones.len()];
+        for (stone, count) in counts {
+            vector[self.indexes[&stone]] = count;
+        }
+        let transitions = self.transitions(blinker);
+        let remaining = blinks - self.warm_up;
+        let terms_needed = 2 * self.stones.len() as u64 + 1;
+        let mut totals = vec![total(ring, &vector)];
+        while (totals.len() as u64) < terms_needed.min(remaining + 1) {
+            vector = blink(ring, &transitions, &vector);
+            totals.push(total(ring, &vector));
+        }
+        if let Some(count) = totals.get(usize::try_from(remaining).unwrap_or(usize::MAX)) {
+            return count.clone();
+        }
+        if let Some(count) = ring.extrapolate(&totals, remaining) {
+            return count;
+        }
+        let mut remaining = remaining - (totals.len() as u64 - 1);
+        // a blink of the counts takes time linear in the size of the set, so blinking is quicker
+        // than squaring the matrix even once until there are that many blinks squared
+        let size = self.stones.len() as u64;
+        if remaining <= size * size {
+            for _ in 0..remaining {
+                vector = blink(ring, &transitions, &vector);
+            }
+            return total(ring, &vector);
+        }
+        let mut power = self.matrix(ring, &transitions);
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                vector = power.apply(ring, &vector);
+            }
+            remaining >>= 1;
+            if remaining > 0 {
+                power = power.square(ring);
+            }
+        }
+        total(ring, &vector)
+    }
+}
+
+fn blink<R: Ring>(ring: &R, transitions: &[Vec<usize>], vector: &[R::Elem]) -> Vec<R::Elem> {
+    let mut blinked = vec![ring.zero(); vector.len()];
+    for (count, new_stones) in vector.iter().zip(transitions) {
+        if !ring.is_zero(count) {
+            for &new_stone in new_stones {
+                blinked[new_stone] = ring.add(&blinked[new_stone], count);
+            }
+        }
+    }
+    blinked
+}
+
+fn total<'a, R: Ring>(ring: &R, counts: impl IntoIterator<Item = &'a R::Elem>) -> R::Elem
+where
+    R::Elem: 'a,
+{
+    counts.into_iter().fold(ring.zero(), |total, count| ring.add(&total, count))
+}
+
+fn add_count<R: Ring>(ring: &R, counts: &mut HashMap<Stone, R::Elem>, stone: Stone, count: &R::Elem) {
+    let total = counts.get(&stone).map_or_else(|| count.clone(), |old| ring.add(old, count));
+    counts.insert(stone, total);
+}
@@ -1,7 +1,8 @@
 mod garden;
 
-use crate::common::DaySpec;
-use crate::day_12::garden::Region;
+use crate::common::{DaySpec, Export, Point};
+use crate::day_12::garden::{Garden, Region};
+use crate::svg::{SvgDocument, SvgElement};
 
 pub const DAY_TWELVE: DaySpec<u32, u32> = DaySpec {
     day_num: 12,
@@ -9,93 +10,135 @@ pub const DAY_TWELVE: DaySpec<u32, u32> = DaySpec {
     part_1,
     part_2_name: "total fencing price with bulk discount",
     part_2,
+    exports: &[
+        Export {
+            name: "regions",
+            description: "each region's measurements, bounding box, enclosing region and fence \
+                outline as polygons of x,y corners",
+            run: export_regions,
+            needs_input: true,
+        },
+        Export {
+            name: "svg",
+            description: "SVG drawing of the regions coloured by plant type, with the fence \
+                segments and the sides they merge into; hover over a region for its measurements",
+            run: export_svg,
+            needs_input: true,
+        },
+    ],
 };
 
 fn part_1(input: &str) -> u32 {
-    parse_input(input).iter()
+    Garden::parse(input).regions.iter()
         .map(|region| (region.area() * region.perimeter()) as u32)
         .sum()
 }
 
 fn part_2(input: &str) -> u32 {
-    parse_input(input).iter()
+    Garden::parse(input).regions.iter()
         .map(|region| (region.area() * region.number_of_sides()) as u32)
         .sum()
 }
 
-fn parse_input(input: &str) -> Vec<Region> {
-    let plant_types: Vec<Vec<u8>> = input.lines()
-        .map(|line| line.bytes().collect())
-        .collect();
-    let mut plants_mapped: Vec<Vec<bool>> = plant_types.iter()
-        .map(|row| vec![false; row.len()])
-        .collect();
-    let regions = build_regions(&plant_types, &mut plants_mapped);
-    regions
+fn export_regions(input: &str, _args: &[String]) -> String {
+    let garden = Garden::parse(input);
+    let containment = garden.containment();
+    let mut output = String::new();
+    for (id, region) in garden.regions.iter().enumerate() {
+        let bounds = region.bounding_box();
+        let inside = containment[id].map_or(String::from("none"), |outer_id| outer_id.to_string());
+        output.push_str(&format!(
+            "{} {}: area {}, perimeter {}, sides {}, corners {}, holes {}, rows {}-{}, columns \
+                {}-{}, inside {}\n",
+            id,
+            char::from(region.plant_type()),
+            region.area(),
+            region.perimeter(),
+            region.number_of_sides(),
+            region.corners(),
+            region.holes().len(),
+            bounds.top,
+            bounds.bottom,
+            bounds.left,
+            bounds.right,
+            inside,
+        ));
+        for (polygon_num, polygon) in region.outline().iter().enumerate() {
+            let kind = if polygon_num == 0 { "outline" } else { "hole" };
+            output.push_str(&format!("  {}: {}\n", kind, polygon_str(polygon)));
+        }
+    }
+    output
 }
 
-fn build_regions(plant_types: &[Vec<u8>], plants_mapped: &mut [Vec<bool>]) -> Vec<Region> {
-    let mut regions = Vec::new();
-    for row in 0..plant_types.len() {
-        for col in 0..plant_types[row].len() {
-            if !plants_mapped[row][col] {
-                let region = build_region(row, col, plant_types, plants_mapped);
-                regions.push(region);
-            }
+const CELL_SIZE: isize = 20;
+
+fn export_svg(input: &str, _args: &[String]) -> String {
+    let garden = Garden::parse(input);
+    let width = input.lines().next().map_or(0, str::len);
+    let length = input.lines().count();
+    let cell_size = usize::try_from(CELL_SIZE).unwrap();
+    let mut svg = SvgDocument::new(width * cell_size, length * cell_size);
+    let mut sides = SvgElement::new("g", &[("stroke", "#c0392b"), ("stroke-width", "4"),
+        ("stroke-linecap", "round"), ("opacity", "0.6")]);
+    let mut fences = SvgElement::new("g", &[("stroke", "black"), ("stroke-width", "1.5")]);
+    for region in &garden.regions {
+        svg.add_element(region_svg(region));
+        for (from, to) in region.sides() {
+            sides.add_child(line_svg(from, to, 0));
+        }
+        for (from, to) in region.fence_segments() {
+            // inset from the ends, so the units the perimeter counts can be told apart
+            fences.add_child(line_svg(from, to, CELL_SIZE / 5));
         }
     }
-    regions
+    svg.add_element(sides);
+    svg.add_element(fences);
+    svg.to_string()
 }
 
-fn build_region(
-    row: usize,
-    col: usize,
-    plant_types: &[Vec<u8>],
-    plants_mapped: &mut [Vec<bool>],
-) -> Region {
-    let mut region = Region::new(plant_types[row][col]);
-    // start with the first plant at the region origin; this function will then recursively
-    // add adjacent plants until the whole region is filled in
-    add_plant_to_region(row, col, plant_types, plants_mapped, &mut region);
-    region
+/// The region's outline filled in, holes and all, in a colour picked from its plant type.
+fn region_svg(region: &Region) -> SvgElement {
+    let path: String = region.outline().iter()
+        .map(|polygon| {
+            let points: Vec<String> = polygon.iter()
+                .map(|point| format!("{} {}", point.x * CELL_SIZE, point.y * CELL_SIZE))
+                .collect();
+            format!("M {} Z", points.join(" L "))
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    let hue = (u32::from(region.plant_type()) * 137) % 360;
+    let fill = format!("hsl({}, 60%, 75%)", hue);
+    let mut element = SvgElement::new("path", &[("d", &path), ("fill", &fill), ("fill-rule", "evenodd")]);
+    element.set_title(&format!(
+        "{}: area {}, perimeter {}, sides {}, price {} (with bulk discount {})",
+        char::from(region.plant_type()),
+        region.area(),
+        region.perimeter(),
+        region.number_of_sides(),
+        region.area() * region.perimeter(),
+        region.area() * region.number_of_sides(),
+    ));
+    element
 }
 
-fn add_plant_to_region(
-    row: usize,
-    col: usize,
-    plant_types: &[Vec<u8>],
-    plants_mapped: &mut [Vec<bool>],
-    region: &mut Region,
-) {
-    if !plants_mapped[row][col] && plant_types[row][col] == region.plant_type() {
-        // part of the same region that we haven't mapped out yet.
-        region.add_plant(
-            isize::try_from(row).unwrap(),
-            isize::try_from(col).unwrap());
-        plants_mapped[row][col] = true;
-        expand_region(row, col, plant_types, plants_mapped, region);
-    }
+fn line_svg(from: Point, to: Point, inset: isize) -> SvgElement {
+    let dir = Point { x: (to.x - from.x).signum(), y: (to.y - from.y).signum() };
+    let coords = [
+        from.x * CELL_SIZE + dir.x * inset,
+        from.y * CELL_SIZE + dir.y * inset,
+        to.x * CELL_SIZE - dir.x * inset,
+        to.y * CELL_SIZE - dir.y * inset,
+    ].map(|coord| coord.to_string());
+    SvgElement::new("line", &[("x1", &coords[0]), ("y1", &coords[1]), ("x2", &coords[2]), ("y2", &coords[3])])
 }
 
-fn expand_region(
-    row: usize,
-    col: usize,
-    plant_types: &[Vec<u8>],
-    plants_mapped: &mut [Vec<bool>],
-    region: &mut Region,
-) {
-    if row > 0 {
-        add_plant_to_region(row - 1, col, plant_types, plants_mapped, region);
-    }
-    if row < plant_types.len() - 1 {
-        add_plant_to_region(row + 1, col, plant_types, plants_mapped, region);
-    }
-    if col > 0 {
-        add_plant_to_region(row, col - 1, plant_types, plants_mapped, region);
-    }
-    if col < plant_types[row].len() - 1 {
-        add_plant_to_region(row, col + 1, plant_types, plants_mapped, region);
-    }
+fn polygon_str(polygon: &[Point]) -> String {
+    polygon.iter()
+        .map(|point| format!("{},{}", point.x, point.y))
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 #[cfg(test)]
@@ -122,4 +165,68 @@ MMMISSJEEE";
     fn part_2_sample() {
         assert_eq!(1206, part_2(INPUT));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn region_geometry() {
+        // the puzzle's example of regions inside another region, where the two B regions only
+        // touch diagonally and so make a single hole in the A region
+        let input = "AAAAAA
+AAABBA
+AAABBA
+ABBAAA
+ABBAAA
+AAAAAA";
+        assert_eq!(368, part_2(input));
+        let garden = Garden::parse(input);
+        assert_eq!(vec![None, Some(0), Some(0)], garden.containment());
+        let outer = &garden.regions[0];
+        assert_eq!(1, outer.holes().len());
+        assert_eq!(8, outer.holes()[0].len());
+        let outline = outer.outline();
+        assert_eq!(2, outline.len());
+        assert_eq!("0,0 6,0 6,6 0,6", polygon_str(&outline[0]));
+        assert_eq!("3,1 3,3 1,3 1,5 3,5 3,3 5,3 5,1", polygon_str(&outline[1]));
+        for region in &garden.regions {
+            let outline_corners: usize = region.outline().iter().map(Vec::len).sum();
+            assert_eq!(region.number_of_sides(), region.corners());
+            assert_eq!(region.corners(), outline_corners);
+        }
+    }
+
+    #[test]
+    fn large_region() {
+        // a single region big enough that recursive flood fill would overflow the stack
+        let input = vec!["A".repeat(300); 300].join("\n");
+        let garden = Garden::parse(&input);
+        assert_eq!(1, garden.regions.len());
+        assert_eq!(90_000, garden.regions[0].area());
+        assert_eq!(4, garden.regions[0].corners());
+    }
+
+    #[test]
+    fn nested_regions() {
+        let input = "AAAAA
+ABBBA
+ABCBA
+ABBBA
+AAAAA";
+        let garden = Garden::parse(input);
+        assert_eq!(vec![None, Some(0), Some(1)], garden.containment());
+        let export = export_regions(input, &[]);
+        assert!(export.starts_with("0 A: area 16, perimeter 32, sides 8, corners 8, holes 1, rows 0-4, \
+            columns 0-4, inside none\n  outline: 0,0 5,0 5,5 0,5\n  hole: 1,1 1,4 4,4 4,1\n"));
+    }
+
+    #[test]
+    fn export_svg_sample() {
+        let svg = export_svg(INPUT, &[]);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"200\" height=\"200\""));
+        let garden = Garden::parse(INPUT);
+        // one filled path per region, then a line for every side and every unit of fence
+        assert_eq!(garden.regions.len(), svg.matches("<path").count());
+        let sides: usize = garden.regions.iter().map(|region| region.number_of_sides()).sum();
+        let perimeter: usize = garden.regions.iter().map(|region| region.perimeter()).sum();
+        assert_eq!(sides + perimeter, svg.matches("<line").count());
+        assert!(svg.contains("<title>R: area 12, perimeter 18, sides 10, price 216 (with bulk discount 120)</title>"));
+    }
+}
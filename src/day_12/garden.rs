@@ -1,5 +1,85 @@
 use std::collections::{HashMap, HashSet};
-use crate::common::Direction;
+use crate::common::{Direction, Point};
+
+/// Every region in a garden, along with which region each plant belongs to.
+pub struct Garden {
+    pub regions: Vec<Region>,
+    region_ids: Vec<Vec<usize>>,
+}
+
+impl Garden {
+    pub fn parse(input: &str) -> Self {
+        let plant_types: Vec<Vec<u8>> = input.lines()
+            .map(|line| line.bytes().collect())
+            .collect();
+        let mut region_ids: Vec<Vec<Option<usize>>> = plant_types.iter()
+            .map(|row| vec![None; row.len()])
+            .collect();
+        let mut regions = Vec::new();
+        for row in 0..plant_types.len() {
+            for col in 0..plant_types[row].len() {
+                if region_ids[row][col].is_some() {
+                    continue;
+                }
+                let plant_type = plant_types[row][col];
+                let mut region = Region::new(plant_type);
+                region_ids[row][col] = Some(regions.len());
+                let mut pending = vec![(row, col)];
+                while let Some((row, col)) = pending.pop() {
+                    region.add_plant(isize::try_from(row).unwrap(), isize::try_from(col).unwrap());
+                    let neighbours = [
+                        (row.wrapping_sub(1), col),
+                        (row + 1, col),
+                        (row, col.wrapping_sub(1)),
+                        (row, col + 1),
+                    ];
+                    for (row, col) in neighbours {
+                        let same_type = plant_types.get(row)
+                            .and_then(|plants| plants.get(col))
+                            .is_some_and(|&neighbour_type| neighbour_type == plant_type);
+                        if same_type && region_ids[row][col].is_none() {
+                            region_ids[row][col] = Some(regions.len());
+                            pending.push((row, col));
+                        }
+                    }
+                }
+                regions.push(region);
+            }
+        }
+        let region_ids = region_ids.into_iter()
+            .map(|row| row.into_iter().map(Option::unwrap).collect())
+            .collect();
+        Garden { regions, region_ids }
+    }
+
+    /// The region each region sits inside, i.e. the one with the smallest hole containing it,
+    /// or `None` for regions that aren't enclosed by anything.
+    pub fn containment(&self) -> Vec<Option<usize>> {
+        let mut enclosing: Vec<Option<(usize, usize)>> = vec![None; self.regions.len()];
+        for (outer_id, outer) in self.regions.iter().enumerate() {
+            for hole in outer.holes() {
+                for &(row, col) in &hole {
+                    let id = self.region_ids[usize::try_from(row).unwrap()][usize::try_from(col).unwrap()];
+                    if enclosing[id].is_none_or(|(_, hole_size)| hole.len() < hole_size) {
+                        enclosing[id] = Some((outer_id, hole.len()));
+                    }
+                }
+            }
+        }
+        enclosing.into_iter()
+            .map(|enclosing| enclosing.map(|(outer_id, _)| outer_id))
+            .collect()
+    }
+}
+
+/// The rows and columns a region's plants span, inclusive.
+#[derive(Copy, Clone)]
+pub struct BoundingBox {
+    pub top: isize,
+    pub left: isize,
+    pub bottom: isize,
+    pub right: isize,
+}
 
 pub struct Region {
     plant_type: u8,
@@ -72,6 +152,147 @@ impl Region {
     pub fn plant_type(&self) -> u8 {
         self.plant_type
     }
+
+    pub fn contains(&self, row: isize, col: isize) -> bool {
+        self.plants.contains_key(&(row, col))
+    }
+
+    /// Counts both outward and inward corners of the fence, which always matches the number of
+    /// sides. Where two plants only touch diagonally, each one has a corner there.
+    pub fn corners(&self) -> usize {
+        let mut corners = 0;
+        for &(row, col) in self.plants.keys() {
+            for (row_offset, col_offset) in [(-1, -1), (-1, 1), (1, -1), (1, 1)] {
+                let vertical = self.contains(row + row_offset, col);
+                let horizontal = self.contains(row, col + col_offset);
+                let diagonal = self.contains(row + row_offset, col + col_offset);
+                if (!vertical && !horizontal) || (vertical && horizontal && !diagonal) {
+                    corners += 1;
+                }
+            }
+        }
+        corners
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox {
+        let rows = self.plants.keys().map(|&(row, _)| row);
+        let cols = self.plants.keys().map(|&(_, col)| col);
+        BoundingBox {
+            top: rows.clone().min().unwrap(),
+            left: cols.clone().min().unwrap(),
+            bottom: rows.max().unwrap(),
+            right: cols.max().unwrap(),
+        }
+    }
+
+    /// The positions not in the region but fully enclosed by it, grouped into connected holes
+    /// (each sorted by row and column). Gaps only connected to the outside diagonally still
+    /// count as outside, since the fence has an opening there.
+    pub fn holes(&self) -> Vec<Vec<(isize, isize)>> {
+        // everything in the bounding box that isn't part of the region, plus a ring around the
+        // outside so the area outside the region is all connected together
+        let BoundingBox { top, left, bottom, right } = self.bounding_box();
+        let mut unvisited: HashSet<(isize, isize)> = ((top - 1)..=(bottom + 1))
+            .flat_map(|row| ((left - 1)..=(right + 1)).map(move |col| (row, col)))
+            .filter(|&(row, col)| !self.contains(row, col))
+            .collect();
+        let mut holes = Vec::new();
+        let mut starts: Vec<(isize, isize)> = unvisited.iter().copied().collect();
+        starts.sort_unstable();
+        for start in starts {
+            if !unvisited.remove(&start) {
+                continue;
+            }
+            let mut component = vec![start];
+            let mut pending = vec![start];
+            while let Some((row, col)) = pending.pop() {
+                for row_offset in -1..=1 {
+                    for col_offset in -1..=1 {
+                        let neighbour = (row + row_offset, col + col_offset);
+                        if unvisited.remove(&neighbour) {
+                            component.push(neighbour);
+                            pending.push(neighbour);
+                        }
+                    }
+                }
+            }
+            // the first position is always the corner of the outside ring
+            if start != (top - 1, left - 1) {
+                component.sort_unstable();
+                holes.push(component);
+            }
+        }
+        holes
+    }
+
+    /// Every unit length of fence, running clockwise around the plant it's next to (so the
+    /// region is always on its right), in order of where they start. There's one for each unit
+    /// the perimeter counts.
+    pub fn fence_segments(&self) -> Vec<(Point, Point)> {
+        let mut segments = Vec::new();
+        for &(row, col) in self.plants.keys() {
+            let corner = |row_offset, col_offset| Point { x: col + col_offset, y: row + row_offset };
+            let sides = [
+                (row - 1, col, corner(0, 0), corner(0, 1)),
+                (row, col + 1, corner(0, 1), corner(1, 1)),
+                (row + 1, col, corner(1, 1), corner(1, 0)),
+                (row, col - 1, corner(1, 0), corner(0, 0)),
+            ];
+            for (neighbour_row, neighbour_col, from, to) in sides {
+                if !self.contains(neighbour_row, neighbour_col) {
+                    segments.push((from, to));
+                }
+            }
+        }
+        segments.sort_unstable_by_key(|(from, to)| (from.y, from.x, to.y, to.x));
+        segments
+    }
+
+    /// The straight runs of fence that `number_of_sides` counts, as their end points.
+    pub fn sides(&self) -> Vec<(Point, Point)> {
+        self.outline().iter()
+            .flat_map(|polygon| {
+                polygon.iter()
+                    .zip(polygon.iter().cycle().skip(1))
+                    .map(|(&from, &to)| (from, to))
+            })
+            .collect()
+    }
+
+    /// The fence as closed polygons, outer boundary first and then one for each hole, each
+    /// listing the fence's corners as `x,y` grid points (so a single plant at row 0, column 0
+    /// has the outline `0,0 1,0 1,1 0,1`). Outer boundaries go clockwise and holes anticlockwise.
+    pub fn outline(&self) -> Vec<Vec<Point>> {
+        let starts = self.fence_segments();
+        let mut segments: HashMap<Point, Vec<Point>> = HashMap::new();
+        for &(from, to) in &starts {
+            segments.entry(from).or_default().push(to);
+        }
+        let mut traced = HashSet::new();
+        let mut polygons = Vec::new();
+        for first in starts {
+            if traced.contains(&first) {
+                continue;
+            }
+            let mut segment = first;
+            let mut corners = Vec::new();
+            loop {
+                traced.insert(segment);
+                let next = next_segment(&segments, segment);
+                if next.1 - next.0 != segment.1 - segment.0 {
+                    corners.push(segment.1);
+                }
+                if next == first {
+                    break;
+                }
+                segment = next;
+            }
+            // the first segment starts at the polygon's top left corner, which the loop finds last
+            corners.rotate_right(1);
+            polygons.push(corners);
+        }
+        polygons
+    }
 }
 
 struct Plant {
@@ -111,3 +332,14 @@ impl Plant {
             .count()
     }
 }
+
+/// The segment the fence carries on along after this one. Where two segments leave the same
+/// point (at a diagonal touch), turning right keeps following the fence round the same plant, so
+/// the polygon passes through that point twice rather than splitting in two there.
+fn next_segment(segments: &HashMap<Point, Vec<Point>>, (from, to): (Point, Point)) -> (Point, Point) {
+    let dir = to - from;
+    let right = to + Point { x: -dir.y, y: dir.x };
+    let outgoing = &segments[&to];
+    let next = outgoing.iter().copied().find(|&next| next == right).unwrap_or(outgoing[0]);
+    (to, next)
+}
@@ -1,4 +1,9 @@
-use crate::common::{DaySpec, Point};
+mod ilp;
+mod solver;
+
+use crate::common::{DaySpec, Export};
+use crate::day_13::ilp::{Unbounded, VectorButton, cheapest};
+use crate::day_13::solver::{Button, Int, solve};
 
 pub const DAY_THIRTEEN: DaySpec<u32, u64> = DaySpec {
     day_num: 13,
@@ -6,83 +11,162 @@ pub const DAY_THIRTEEN: DaySpec<u32, u64> = DaySpec {
     part_1,
     part_2_name: "actual fewest tokens needed",
     part_2,
+    exports: &[
+        Export {
+            name: "presses",
+            description: "the cheapest presses of each button for every machine, which can have \
+                any number of buttons and dimensions; optional args are costs=A,B,... (default \
+                3 for A and 1 for the rest), limit=N (default none) and offset=N (added to every \
+                prize coordinate, default 0)",
+            run: export_presses,
+            needs_input: true,
+        },
+    ],
 };
 
+const UNIT_CORRECTION: Int = 10_000_000_000_000;
+
 fn part_1(input: &str) -> u32 {
-    parse_input(input).into_iter()
-        .map(|claw_machine| token_spend(claw_machine, false) as u32)
-        .sum()
+    u32::try_from(fewest_tokens(input, &Settings { limit: Some(100), ..Settings::puzzle() })).unwrap()
 }
 
 fn part_2(input: &str) -> u64 {
-    parse_input(input).into_iter()
-        .map(|claw_machine| token_spend(claw_machine, true) as u64)
+    u64::try_from(fewest_tokens(input, &Settings { offset: UNIT_CORRECTION, ..Settings::puzzle() })).unwrap()
+}
+
+/// The tokens needed to win every prize that can be won. The puzzle's machines have two
+/// buttons, which are never unbounded; the presses export reports machines that are.
+fn fewest_tokens(input: &str, settings: &Settings) -> Int {
+    parse_input(input).iter()
+        .filter_map(|claw_machine| {
+            claw_machine.cheapest(settings)
+                .unwrap_or_else(|unbounded| panic!("{}", unbounded))
+        })
+        .map(|(_, cost)| cost)
         .sum()
 }
 
-fn token_spend(claw_machine: ClawMachine, unit_correction: bool) -> isize {
-    let ClawMachine {
-        button_a: Point { x: a_x, y: a_y },
-        button_b: Point { x: b_x, y: b_y },
-        prize: Point { x: mut prize_x, y: mut prize_y }
-    } = claw_machine;
-    if unit_correction {
-        prize_x += 10_000_000_000_000;
-        prize_y += 10_000_000_000_000;
+fn export_presses(input: &str, args: &[String]) -> String {
+    let mut settings = Settings::puzzle();
+    for arg in args {
+        let (key, value) = arg.split_once('=').unwrap_or_else(|| panic!("unknown option: {}", arg));
+        match key {
+            "costs" => settings.costs = value.split(',').map(|cost| cost.parse().unwrap()).collect(),
+            "limit" => settings.limit = Some(value.parse().unwrap()),
+            "offset" => settings.offset = value.parse().unwrap(),
+            _ => panic!("unknown option: {}", key),
+        }
+    }
+    let mut output = String::new();
+    let mut total = 0;
+    for (machine_num, claw_machine) in parse_input(input).iter().enumerate() {
+        match claw_machine.cheapest(&settings) {
+            Ok(Some((presses, cost))) => {
+                let presses: Vec<String> = claw_machine.labels.iter()
+                    .zip(presses)
+                    .map(|(label, count)| format!("{} {} times", label, count))
+                    .collect();
+                output.push_str(&format!("{}: {}, {} tokens\n", machine_num, presses.join(", "), cost));
+                total += cost;
+            }
+            Ok(None) => output.push_str(&format!("{}: no way to win\n", machine_num)),
+            Err(Unbounded { button }) => output.push_str(&format!(
+                "{}: can't search every way to win, as presses of {} aren't limited or bounded by the prize\n",
+                machine_num,
+                claw_machine.labels[button],
+            )),
+        }
     }
-    let a_numerator = (b_x * prize_y) - (b_y * prize_x);
-    let a_denominator = (a_y * b_x) - (a_x * b_y);
-    let b_numerator = (a_x * prize_y) - (a_y * prize_x);
-    let b_denominator = (a_x * b_y) - (a_y * b_x);
-    if a_numerator % a_denominator != 0 || b_numerator % b_denominator != 0 {
-        return 0;
+    output.push_str(&format!("total {} tokens\n", total));
+    output
+}
+
+/// How the buttons are charged for and limited, and how far the prize really is. Buttons past
+/// the end of `costs` cost the same as the last one listed.
+struct Settings {
+    costs: Vec<Int>,
+    limit: Option<Int>,
+    offset: Int,
+}
+
+impl Settings {
+    fn puzzle() -> Self {
+        Settings { costs: vec![3, 1], limit: None, offset: 0 }
     }
-    let a_presses = a_numerator / a_denominator;
-    let b_presses = b_numerator / b_denominator;
-    if a_presses < 1 || b_presses < 1 ||
-        (!unit_correction && (a_presses > 100 || b_presses > 100)) {
-        return 0;
+
+    fn cost(&self, button: usize) -> Int {
+        *self.costs.get(button).or(self.costs.last()).expect("at least one cost should be given")
     }
-    (a_presses * 3) + b_presses
 }
 
 fn parse_input(input: &str) -> Vec<ClawMachine> {
-    let mut claw_machines = Vec::new();
-    let mut lines = input.lines();
-    loop {
-        let button_a = lines.next().unwrap();
-        let button_b = lines.next().unwrap();
-        let prize = lines.next().unwrap();
-        let claw_machine = ClawMachine::parse(button_a, button_b, prize);
-        claw_machines.push(claw_machine);
-        if lines.next().is_none() {
-            break;
-        }
-    }
-    claw_machines
+    input.split("\n\n")
+        .map(ClawMachine::parse)
+        .collect()
 }
 
+/// A claw machine with any number of buttons, in any number of dimensions.
 struct ClawMachine {
-    button_a: Point,
-    button_b: Point,
-    prize: Point,
+    labels: Vec<String>,
+    buttons: Vec<Vec<Int>>,
+    prize: Vec<Int>,
 }
 
 impl ClawMachine {
-    fn parse(button_a_str: &str, button_b_str: &str, prize_str: &str) -> Self {
-        let button_a = ClawMachine::parse_line(button_a_str);
-        let button_b = ClawMachine::parse_line(button_b_str);
-        let prize = ClawMachine::parse_line(prize_str);
-        Self { button_a, button_b, prize }
+    fn parse(machine_str: &str) -> Self {
+        let mut labels = Vec::new();
+        let mut buttons = Vec::new();
+        let mut prize = Vec::new();
+        for line in machine_str.lines() {
+            let (name, values) = line.split_once(": ").unwrap();
+            let values = ClawMachine::parse_values(values);
+            if let Some(label) = name.strip_prefix("Button ") {
+                labels.push(label.to_string());
+                buttons.push(values);
+            } else {
+                prize = values;
+            }
+        }
+        Self { labels, buttons, prize }
     }
 
-    fn parse_line(line: &str) -> Point {
-        let parts: Vec<&str> = line.split(": ").collect::<Vec<&str>>()[1]
-            .split(", ").collect();
-        let x = parts[0][2..].parse().unwrap();
-        let y = parts[1][2..].parse().unwrap();
-        Point { x, y }
+    /// Values such as `X+94`, `Y-3` or `X=8400`.
+    fn parse_values(values: &str) -> Vec<Int> {
+        values.split(", ")
+            .map(|value| value[1..].trim_start_matches(['+', '=']).parse().unwrap())
+            .collect()
+    }
 
+    /// The cheapest presses that win the prize, along with what they cost, or `None` if it
+    /// can't be won. Two buttons in two dimensions have a direct solution; anything else is
+    /// solved as an integer linear program, which fails if a button isn't bounded.
+    fn cheapest(&self, settings: &Settings) -> Result<Option<(Vec<Int>, Int)>, Unbounded> {
+        let prize: Vec<Int> = self.prize.iter().map(|&coord| coord + settings.offset).collect();
+        let presses = if let ([button_a, button_b], [prize_x, prize_y]) = (self.buttons.as_slice(), prize.as_slice()) {
+            let button = |movement: &[Int], cost| Button { x: movement[0], y: movement[1], cost, limit: settings.limit };
+            solve(&button(button_a, settings.cost(0)), &button(button_b, settings.cost(1)), (*prize_x, *prize_y))
+                .map(|presses| vec![presses.a, presses.b])
+        } else {
+            cheapest(&self.vector_buttons(settings), &prize)?
+        };
+        Ok(presses.map(|presses| {
+            let cost = presses.iter()
+                .enumerate()
+                .map(|(button, &count)| count * settings.cost(button))
+                .sum();
+            (presses, cost)
+        }))
+    }
+
+    fn vector_buttons(&self, settings: &Settings) -> Vec<VectorButton> {
+        self.buttons.iter()
+            .enumerate()
+            .map(|(button, movement)| VectorButton {
+                movement: movement.clone(),
+                cost: settings.cost(button),
+                limit: settings.limit,
+            })
+            .collect()
     }
 }
 
@@ -90,9 +174,7 @@ impl ClawMachine {
 mod tests {
     use super::*;
 
-    #[test]
-    fn part_1_sample() {
-        let input = "Button A: X+94, Y+34
+    const INPUT: &str = "Button A: X+94, Y+34
 Button B: X+22, Y+67
 Prize: X=8400, Y=5400
 
@@ -107,6 +189,99 @@ Prize: X=7870, Y=6450
 Button A: X+69, Y+23
 Button B: X+27, Y+71
 Prize: X=18641, Y=10279";
-        assert_eq!(480, part_1(input));
+
+    #[test]
+    fn part_1_sample() {
+        assert_eq!(480, part_1(INPUT));
+    }
+
+    #[test]
+    fn part_2_sample() {
+        // only the second and fourth machines can be won
+        assert_eq!(875318608908, part_2(INPUT));
+        let output = export_presses(INPUT, &[String::from("offset=10000000000000")]);
+        assert!(output.starts_with("0: no way to win\n1: A 118679050709 times, B 103199174542 times"));
+
+        // the general solver agrees on the two button machines
+        for settings in [Settings { limit: Some(100), ..Settings::puzzle() }, Settings { offset: UNIT_CORRECTION, ..Settings::puzzle() }] {
+            for claw_machine in parse_input(INPUT) {
+                let prize: Vec<Int> = claw_machine.prize.iter().map(|&coord| coord + settings.offset).collect();
+                let general = cheapest(&claw_machine.vector_buttons(&settings), &prize).unwrap();
+                assert_eq!(claw_machine.cheapest(&settings).unwrap().map(|(presses, _)| presses), general);
+            }
+        }
+    }
+
+    #[test]
+    fn more_buttons() {
+        // three buttons in three dimensions, where C is the cheapest way to move but only comes
+        // in big steps
+        let input = "Button A: X+1, Y+0, Z+2
+Button B: X+0, Y+1, Z+1
+Button C: X+5, Y+5, Z+15
+Prize: X=12, Y=11, Z=35
+
+Button A: X+2, Y+1
+Button B: X+1, Y+2
+Button C: X+1, Y+1
+Prize: X=9, Y=9";
+        let output = export_presses(input, &[String::from("costs=3,1,2")]);
+        // 12 A presses and 11 B presses would cost 47 tokens
+        assert_eq!("0: A 2 times, B 1 times, C 2 times, 11 tokens
+1: A 3 times, B 3 times, C 0 times, 12 tokens
+total 23 tokens
+", output);
+        let output = export_presses(input, &[String::from("costs=3,1,7"), String::from("limit=4")]);
+        assert_eq!("0: A 2 times, B 1 times, C 2 times, 21 tokens
+1: A 3 times, B 3 times, C 0 times, 12 tokens
+total 33 tokens
+", output);
+        let output = export_presses(input, &[String::from("limit=1")]);
+        assert_eq!("0: no way to win\n1: no way to win\ntotal 0 tokens\n", output);
+
+        // C and D move backwards along every dimension, so without a limit there's no telling
+        // how many presses of C to try
+        let input = "Button A: X+1, Y+0
+Button B: X+0, Y+1
+Button C: X-1, Y-1
+Button D: X-2, Y-1
+Prize: X=2, Y=3";
+        let machine = &parse_input(input)[0];
+        assert_eq!(Err(Unbounded { button: 2 }), machine.cheapest(&Settings::puzzle()));
+        let output = export_presses(input, &[]);
+        assert_eq!("0: can't search every way to win, as presses of C aren't limited or bounded by the prize
+total 0 tokens
+", output);
+        let output = export_presses(input, &[String::from("costs=3,1,0,0"), String::from("limit=5")]);
+        assert_eq!("0: A 2 times, B 3 times, C 0 times, D 0 times, 9 tokens\ntotal 9 tokens\n", output);
+    }
+
+    fn presses(button_a: Button, button_b: Button, prize: (Int, Int)) -> Option<(Int, Int)> {
+        solve(&button_a, &button_b, prize).map(|presses| (presses.a, presses.b))
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn collinear_buttons() {
+        let button = |x, y, cost| Button { x, y, cost, limit: None };
+        // B moves twice as far as A for less than twice the cost, so use as many Bs as possible
+        assert_eq!(Some((1, 3)), presses(button(2, 4, 3), button(4, 8, 5), (14, 28)));
+        // ...unless it costs more than two As
+        assert_eq!(Some((7, 0)), presses(button(2, 4, 3), button(4, 8, 7), (14, 28)));
+        // ...or there's a limit on pressing it
+        let limited = Button { limit: Some(2), ..button(4, 8, 5) };
+        assert_eq!(Some((3, 2)), presses(button(2, 4, 3), limited, (14, 28)));
+        // only multiples of the gcd along the line are reachable, and nothing off the line
+        assert_eq!(None, presses(button(4, 6, 3), button(8, 12, 1), (2, 3)));
+        assert_eq!(None, presses(button(4, 6, 3), button(6, 9, 1), (11, 17)));
+        assert_eq!(Some((2, 1)), presses(button(4, 6, 1), button(6, 9, 3), (14, 21)));
+        // buttons that move in opposite directions
+        assert_eq!(Some((0, 0)), presses(button(3, 3, 1), button(-2, -2, 1), (0, 0)));
+        assert_eq!(Some((1, 1)), presses(button(3, 3, 1), button(-2, -2, 1), (1, 1)));
+        // a button that doesn't move at all
+        assert_eq!(Some((0, 4)), presses(button(0, 0, 0), button(2, 1, 1), (8, 4)));
+        assert_eq!(Some((0, 0)), presses(button(0, 0, 1), button(0, 0, 1), (0, 0)));
+        assert_eq!(None, presses(button(0, 0, 1), button(0, 0, 1), (1, 0)));
+        // zero presses of one button is allowed
+        assert_eq!(Some((0, 5)), presses(button(3, 1, 3), button(1, 2, 1), (5, 10)));
+    }
+}
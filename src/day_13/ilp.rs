@@ -0,0 +1,260 @@
+use crate::day_13::solver::Int;
+use num::Integer;
+use num::rational::Ratio;
+use std::fmt;
+use std::fmt::Display;
+
+type Rational = Ratio<Int>;
+
+/// A button that moves the claw by some vector each time it's pressed.
+pub struct VectorButton {
+    pub movement: Vec<Int>,
+    pub cost: Int,
+    pub limit: Option<Int>,
+}
+
+/// A button that has to be branched on, but that has no limit and isn't bounded by the prize
+/// either, so there's no end to the presses of it to try.
+#[derive(Debug, PartialEq)]
+pub struct Unbounded {
+    pub button: usize,
+}
+
+impl Display for Unbounded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "presses of button {} aren't limited or bounded by the prize", self.button)
+    }
+}
+
+/// The cheapest presses of each button that moves the claw exactly onto the prize, as an
+/// integer linear program: minimise the cost of `presses` subject to `buttons * presses =
+/// prize` with every press count between 0 and its button's limit.
+///
+/// Row reducing the buttons picks out a set of pivot buttons whose presses are fixed once the
+/// other (free) buttons' presses are chosen. All but the last free button are branched on, which
+/// needs them to have limits or be bounded by the prize, and then the last one is solved
+/// exactly: the pivot presses change linearly with it, so the allowed values form arithmetic
+/// progressions and the cheapest is at one end of one of them. Costs must not be negative.
+///
+/// Returns `None` if the prize can't be reached, and an error if a button that needs branching
+/// on isn't bounded.
+pub fn cheapest(buttons: &[VectorButton], prize: &[Int]) -> Result<Option<Vec<Int>>, Unbounded> {
+    assert!(buttons.iter().all(|button| button.cost >= 0), "button costs can't be negative");
+    let Some(reduced) = Reduced::new(buttons, prize) else {
+        return Ok(None);
+    };
+    let mut search = Search { buttons, prize, reduced: &reduced, best: None };
+    let mut presses = vec![0; buttons.len()];
+    search.branch(0, &mut presses)?;
+    Ok(search.best.map(|(presses, _)| presses))
+}
+
+/// The buttons and prize in reduced row echelon form, where each row gives one pivot button's
+/// presses in terms of the free buttons' presses.
+struct Reduced {
+    pivots: Vec<usize>,
+    free: Vec<usize>,
+    rows: Vec<Vec<Rational>>,
+}
+
+impl Reduced {
+    /// Returns `None` if the prize isn't reachable even allowing fractional presses.
+    fn new(buttons: &[VectorButton], prize: &[Int]) -> Option<Self> {
+        let columns = buttons.len();
+        let mut rows: Vec<Vec<Rational>> = prize.iter()
+            .enumerate()
+            .map(|(dim, &target)| {
+                let mut row: Vec<Rational> = buttons.iter()
+                    .map(|button| Rational::from_integer(button.movement[dim]))
+                    .collect();
+                row.push(Rational::from_integer(target));
+                row
+            })
+            .collect();
+        let mut pivots = Vec::new();
+        for col in 0..columns {
+            let rank = pivots.len();
+            let Some(pivot_row) = (rank..rows.len()).find(|&row| rows[row][col] != Rational::ZERO) else {
+                continue;
+            };
+            rows.swap(rank, pivot_row);
+            let pivot = rows[rank][col];
+            for value in rows[rank].iter_mut() {
+                *value /= pivot;
+            }
+            let pivot_row = rows[rank].clone();
+            for (row, values) in rows.iter_mut().enumerate() {
+                let factor = values[col];
+                if row != rank && factor != Rational::ZERO {
+                    for (value, pivot_value) in values.iter_mut().zip(&pivot_row).skip(col) {
+                        *value -= factor * pivot_value;
+                    }
+                }
+            }
+            pivots.push(col);
+        }
+        // rows past the rank have no buttons left in them, so need nothing left to reach either
+        if rows[pivots.len()..].iter().any(|row| row[columns] != Rational::ZERO) {
+            return None;
+        }
+        rows.truncate(pivots.len());
+        let free = (0..columns).filter(|col| !pivots.contains(col)).collect();
+        Some(Reduced { pivots, free, rows })
+    }
+
+    /// The pivot button's presses with every free button's presses as given, apart from the
+    /// `last` free button, as `constant + slope * last presses`.
+    fn pivot_presses(&self, row: usize, presses: &[Int], last: Option<usize>) -> (Rational, Rational) {
+        let values = &self.rows[row];
+        let mut constant = values[values.len() - 1];
+        let mut slope = Rational::ZERO;
+        for &col in &self.free {
+            if Some(col) == last {
+                slope = -values[col];
+            } else {
+                constant -= values[col] * presses[col];
+            }
+        }
+        (constant, slope)
+    }
+}
+
+struct Search<'a> {
+    buttons: &'a [VectorButton],
+    prize: &'a [Int],
+    reduced: &'a Reduced,
+    best: Option<(Vec<Int>, Int)>,
+}
+
+impl Search<'_> {
+    fn branch(&mut self, depth: usize, presses: &mut Vec<Int>) -> Result<(), Unbounded> {
+        let free = &self.reduced.free;
+        if depth + 1 >= free.len() {
+            self.solve_last(presses, free.last().copied());
+            return Ok(());
+        }
+        let button = free[depth];
+        let max = self.max_presses(button).ok_or(Unbounded { button })?;
+        for count in 0..=max {
+            presses[button] = count;
+            // with no negative costs, pressing more can only cost more
+            let cost_so_far = free[..=depth].iter()
+                .map(|&col| presses[col] * self.buttons[col].cost)
+                .sum::<Int>();
+            if self.best.as_ref().is_some_and(|(_, best_cost)| cost_so_far > *best_cost) {
+                break;
+            }
+            self.branch(depth + 1, presses)?;
+        }
+        presses[button] = 0;
+        Ok(())
+    }
+
+    /// The most a button can be pressed: its limit, or how many presses take it past the prize
+    /// along a dimension that no button ever moves backwards in.
+    fn max_presses(&self, button: usize) -> Option<Int> {
+        let by_prize = (0..self.prize.len())
+            .filter(|&dim| {
+                self.buttons[button].movement[dim] > 0 &&
+                    self.buttons.iter().all(|other| other.movement[dim] >= 0)
+            })
+            .map(|dim| Integer::div_floor(&self.prize[dim], &self.buttons[button].movement[dim]).max(-1))
+            .min();
+        match (self.buttons[button].limit, by_prize) {
+            (Some(limit), Some(by_prize)) => Some(limit.min(by_prize)),
+            (limit, by_prize) => limit.or(by_prize),
+        }
+    }
+
+    fn solve_last(&mut self, presses: &mut [Int], last: Option<usize>) {
+        let pivot_presses: Vec<(Rational, Rational)> = (0..self.reduced.pivots.len())
+            .map(|row| self.reduced.pivot_presses(row, presses, last))
+            .collect();
+        let Some(last) = last else {
+            // the pivot presses are all fixed, although not necessarily in range
+            if pivot_presses.iter().all(|(constant, _)| constant.is_integer()) {
+                for (&col, (constant, _)) in self.reduced.pivots.iter().zip(&pivot_presses) {
+                    presses[col] = constant.to_integer();
+                }
+                self.consider(presses);
+            }
+            return;
+        };
+
+        // the range of presses of the last free button that keeps every button's presses in range
+        let mut low = Rational::ZERO;
+        let mut high = self.buttons[last].limit.map(Rational::from_integer);
+        for (&col, &(constant, slope)) in self.reduced.pivots.iter().zip(&pivot_presses) {
+            // 0 <= constant + slope * x <= limit
+            let limit = self.buttons[col].limit.map(Rational::from_integer);
+            if slope == Rational::ZERO {
+                if constant < Rational::ZERO || limit.is_some_and(|limit| constant > limit) {
+                    return;
+                }
+                continue;
+            }
+            let zero_at = -constant / slope;
+            let limit_at = limit.map(|limit| (limit - constant) / slope);
+            let (min, max) = if slope > Rational::ZERO { (Some(zero_at), limit_at) } else { (limit_at, Some(zero_at)) };
+            if let Some(min) = min {
+                low = low.max(min);
+            }
+            if let Some(max) = max {
+                high = Some(high.map_or(max, |high| high.min(max)));
+            }
+        }
+        let low = low.ceil().to_integer();
+        let high = high.map(|high| high.floor().to_integer());
+        if high.is_some_and(|high| high < low) {
+            return;
+        }
+
+        // the pivot presses are only whole numbers for some values of x, repeating with a period
+        // of the slopes' denominators
+        let period = pivot_presses.iter().fold(1, |period: Int, (_, slope)| period.lcm(slope.denom()));
+        let cost_slope = pivot_presses.iter()
+            .zip(&self.reduced.pivots)
+            .fold(Rational::from_integer(self.buttons[last].cost), |total, (&(_, slope), &col)| {
+                total + slope * self.buttons[col].cost
+            });
+        for offset in 0..period {
+            let first = low + offset;
+            if high.is_some_and(|high| first > high) {
+                break;
+            }
+            let whole = pivot_presses.iter()
+                .all(|&(constant, slope)| (constant + slope * first).is_integer());
+            if !whole {
+                continue;
+            }
+            // the cost can't keep falling forever without a press count going negative, so
+            // there's always a high end to go to when it's cheaper
+            let count = match high {
+                Some(high) if cost_slope < Rational::ZERO => first + (high - first) / period * period,
+                _ => first,
+            };
+            presses[last] = count;
+            for (&col, &(constant, slope)) in self.reduced.pivots.iter().zip(&pivot_presses) {
+                presses[col] = (constant + slope * count).to_integer();
+            }
+            self.consider(presses);
+        }
+        presses[last] = 0;
+    }
+
+    fn consider(&mut self, presses: &[Int]) {
+        let allowed = presses.iter()
+            .zip(self.buttons)
+            .all(|(&count, button)| count >= 0 && button.limit.is_none_or(|limit| count <= limit));
+        if !allowed {
+            return;
+        }
+        let cost = presses.iter()
+            .zip(self.buttons)
+            .map(|(&count, button)| count * button.cost)
+            .sum();
+        if self.best.as_ref().is_none_or(|(_, best_cost)| cost < *best_cost) {
+            self.best = Some((presses.to_vec(), cost));
+        }
+    }
+}
@@ -0,0 +1,168 @@
+pub type Int = i128;
+
+/// A button that moves the claw by `x` and `y` each time it's pressed, costing `cost` tokens,
+/// optionally only allowed to be pressed up to `limit` times.
+#[derive(Copy, Clone)]
+pub struct Button {
+    pub x: Int,
+    pub y: Int,
+    pub cost: Int,
+    pub limit: Option<Int>,
+}
+
+/// How many times to press each of a pair of buttons.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Presses {
+    pub a: Int,
+    pub b: Int,
+}
+
+/// The cheapest number of presses of each button that moves the claw exactly onto the prize,
+/// if there is one. Costs must not be negative.
+pub fn solve(button_a: &Button, button_b: &Button, prize: (Int, Int)) -> Option<Presses> {
+    assert!(button_a.cost >= 0 && button_b.cost >= 0, "button costs can't be negative");
+    let determinant = sub(mul(button_a.x, button_b.y), mul(button_a.y, button_b.x));
+    let presses = if determinant != 0 {
+        // the buttons move in different directions, so there's only one way to get anywhere
+        let a = sub(mul(prize.0, button_b.y), mul(prize.1, button_b.x));
+        let b = sub(mul(button_a.x, prize.1), mul(button_a.y, prize.0));
+        if a % determinant != 0 || b % determinant != 0 {
+            return None;
+        }
+        Presses { a: a / determinant, b: b / determinant }
+    } else {
+        solve_collinear(button_a, button_b, prize)?
+    };
+    let within_limit = |presses: Int, button: &Button| {
+        presses >= 0 && button.limit.is_none_or(|limit| presses <= limit)
+    };
+    if within_limit(presses.a, button_a) && within_limit(presses.b, button_b) {
+        Some(presses)
+    } else {
+        None
+    }
+}
+
+/// When both buttons move along the same line, the prize has to be on that line too, and then
+/// there can be many ways to reach it: every solution to `a * p + b * q = prize` along the line
+/// is one particular solution plus some multiple of a fixed step, found with the extended
+/// Euclidean algorithm. The cost changes linearly with that multiple, so the cheapest solution
+/// is at one end of the range that keeps both press counts allowed.
+fn solve_collinear(button_a: &Button, button_b: &Button, prize: (Int, Int)) -> Option<Presses> {
+    // the direction of the line, reduced so that each button and the prize move a whole number
+    // of steps along it
+    let Some(direction) = [(button_a.x, button_a.y), (button_b.x, button_b.y)].into_iter()
+        .find(|&movement| movement != (0, 0))
+        .map(|(x, y)| {
+            let divisor = gcd(x, y);
+            (x / divisor, y / divisor)
+        }) else {
+        // neither button does anything
+        return (prize == (0, 0)).then_some(Presses { a: 0, b: 0 });
+    };
+    if sub(mul(prize.0, direction.1), mul(prize.1, direction.0)) != 0 {
+        return None;
+    }
+    let steps = |x: Int, y: Int| if direction.0 != 0 { x / direction.0 } else { y / direction.1 };
+    let (a_steps, b_steps) = (steps(button_a.x, button_a.y), steps(button_b.x, button_b.y));
+    let (prize_x, prize_y) = prize;
+    if (direction.0 != 0 && prize_x % direction.0 != 0) || (direction.0 == 0 && prize_y % direction.1 != 0) {
+        return None;
+    }
+    let prize_steps = steps(prize_x, prize_y);
+    if a_steps == 0 {
+        // button A does nothing, so it's never worth pressing
+        return (prize_steps % b_steps == 0).then_some(Presses { a: 0, b: prize_steps / b_steps });
+    }
+    if b_steps == 0 {
+        return (prize_steps % a_steps == 0).then_some(Presses { a: prize_steps / a_steps, b: 0 });
+    }
+
+    let (divisor, a_coefficient, b_coefficient) = extended_gcd(a_steps, b_steps);
+    if prize_steps % divisor != 0 {
+        return None;
+    }
+    let scale = prize_steps / divisor;
+    let (a_base, b_base) = (mul(a_coefficient, scale), mul(b_coefficient, scale));
+    // every solution is a = a_base + k * a_step, b = b_base - k * b_step
+    let (a_step, b_step) = (b_steps / divisor, a_steps / divisor);
+
+    // the range of k keeping each press count between 0 and its limit (if any)
+    let mut k_min = None;
+    let mut k_max = None;
+    let mut bound = |base: Int, step: Int, limit: Option<Int>| {
+        // base + k * step must be in 0..=limit
+        let (at_least, at_most) = (neg(base), limit.map(|limit| sub(limit, base)));
+        let (min, max) = if step > 0 {
+            (Some(div_ceil(at_least, step)), at_most.map(|at_most| div_floor(at_most, step)))
+        } else {
+            (at_most.map(|at_most| div_ceil(at_most, step)), Some(div_floor(at_least, step)))
+        };
+        k_min = k_min.max(min);
+        k_max = match (k_max, max) {
+            (Some(k_max), Some(max)) => Some(Int::min(k_max, max)),
+            (k_max, max) => k_max.or(max),
+        };
+    };
+    bound(a_base, a_step, button_a.limit);
+    bound(b_base, neg(b_step), button_b.limit);
+    if let (Some(k_min), Some(k_max)) = (k_min, k_max) && k_min > k_max {
+        return None;
+    }
+
+    let cost_per_k = sub(mul(a_step, button_a.cost), mul(b_step, button_b.cost));
+    // with no negative costs, the cost can only fall forever in a direction where one of the
+    // press counts would go negative, so the end needed here is always bounded
+    let cheapest_end = if cost_per_k > 0 || (cost_per_k == 0 && k_min.is_some()) { k_min } else { k_max };
+    let k = cheapest_end.or(k_min).or(k_max).unwrap_or(0);
+    Some(Presses { a: add(a_base, mul(k, a_step)), b: sub(b_base, mul(k, b_step)) })
+}
+
+/// Returns `(g, x, y)` where `g` is the (non-negative) greatest common divisor of `a` and `b`,
+/// and `a * x + b * y = g`.
+fn extended_gcd(a: Int, b: Int) -> (Int, Int, Int) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (1, 0);
+    let (mut old_y, mut y) = (0, 1);
+    while r != 0 {
+        let quotient = old_r / r;
+        (old_r, r) = (r, sub(old_r, mul(quotient, r)));
+        (old_x, x) = (x, sub(old_x, mul(quotient, x)));
+        (old_y, y) = (y, sub(old_y, mul(quotient, y)));
+    }
+    if old_r < 0 {
+        (neg(old_r), neg(old_x), neg(old_y))
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+fn gcd(a: Int, b: Int) -> Int {
+    extended_gcd(a, b).0
+}
+
+fn div_floor(numerator: Int, denominator: Int) -> Int {
+    let quotient = numerator / denominator;
+    if numerator % denominator != 0 && (numerator < 0) != (denominator < 0) { quotient - 1 } else { quotient }
+}
+
+fn div_ceil(numerator: Int, denominator: Int) -> Int {
+    let quotient = numerator / denominator;
+    if numerator % denominator != 0 && (numerator < 0) == (denominator < 0) { quotient + 1 } else { quotient }
+}
+
+fn add(left: Int, right: Int) -> Int {
+    left.checked_add(right).expect("claw machine arithmetic overflowed")
+}
+
+fn sub(left: Int, right: Int) -> Int {
+    left.checked_sub(right).expect("claw machine arithmetic overflowed")
+}
+
+fn mul(left: Int, right: Int) -> Int {
+    left.checked_mul(right).expect("claw machine arithmetic overflowed")
+}
+
+fn neg(value: Int) -> Int {
+    value.checked_neg().expect("claw machine arithmetic overflowed")
+}
@@ -1,163 +1,222 @@
-use std::collections::HashMap;
-use crate::common::{DaySpec, Point};
+mod swarm;
 
-pub const DAY_FOURTEEN: DaySpec<u32, usize> = DaySpec {
+use std::fs;
+use crate::common::{DaySpec, Dimensions, Export, Point};
+use crate::day_14::swarm::{DETECTORS, Detector, Frame, Robot, Swarm};
+use crate::image::{GreyImage, ImageFormat};
+
+pub const DAY_FOURTEEN: DaySpec<u64, String> = DaySpec {
     day_num: 14,
     part_1_name: "safety factor after 100 seconds",
     part_1,
     part_2_name: "seconds for first Christmas tree",
     part_2,
+    exports: &[
+        Export {
+            name: "candidates",
+            description: "the times most likely to show a picture, with each one's statistics; \
+                optional args are width=N and length=N (default 101 by 103), top=N (default 10) \
+                and detectors=a,b,... from variance, entropy, safety and component (default all)",
+            run: export_candidates,
+            needs_input: true,
+        },
+        Export {
+            name: "image",
+            description: "writes an image of the robots at=N seconds (default 0); optional args \
+                are width=N and length=N (default 101 by 103), format=pbm|pgm|png (default png), \
+                scale=N pixels per tile (default 1) and out=PATH",
+            run: export_image,
+            needs_input: true,
+        },
+        Export {
+            name: "frames",
+            description: "writes an image for every second from=N (default 0) up to but not \
+                including to=N (default 100 seconds later) into the directory out=DIR (default \
+                the current one), taking the same width, length, format and scale args as image",
+            run: export_frames,
+            needs_input: true,
+        },
+        Export {
+            name: "sheet",
+            description: "writes a contact sheet of the top=N (default 10) candidate times, \
+                columns=N across (default 5), to out=PATH; takes the detectors arg of candidates \
+                and the width, length, format and scale args of image",
+            run: export_sheet,
+            needs_input: true,
+        },
+    ],
 };
 
-fn part_1(input: &str) -> u32 {
-    part_1_sized(input, 101, 103)
-}
-
-fn part_1_sized(input: &str, width: usize, length: usize) -> u32 {
-    let robots = parse_input(input);
-    let mut quadrants: HashMap<Quadrant, u32> = [
-        Quadrant::UpperLeft,
-        Quadrant::UpperRight,
-        Quadrant::LowerLeft,
-        Quadrant::LowerRight,
-        ]
-        .iter()
-        .map(|&quadrant| (quadrant, 0))
-        .collect();
-
-    for robot in robots {
-        let x = calc_position_after(robot.position.x, robot.velocity.x, width, 100);
-        let y = calc_position_after(robot.position.y, robot.velocity.y, length, 100);
-        match determine_quadrant(x, y, width, length) {
-            Quadrant::None => {},
-            q => {
-                quadrants.entry(q)
-                    .and_modify(|count| { *count += 1 });
-            },
-        };
-    }
-    quadrants.values()
-        .fold(1, |quadrant, count| quadrant * count)
-}
-
-fn part_2(input: &str) -> usize {
-    part_2_sized(input, 101, 103)
-}
-
-fn part_2_sized(input: &str, width: usize, length: usize) -> usize {
-    let robots = parse_input(input);
-    let mut horizontal_band_offset = None;
-    let mut vertical_band_offset = None;
-    let mut i = 0;
-    let mut by_x = vec![RobotsAfter { seconds: 0, count: 0 }; width];
-    let mut by_y = vec![RobotsAfter { seconds: 0, count : 0}; length];
-    while i < usize::max(width, length) &&
-        (horizontal_band_offset.is_none() || vertical_band_offset.is_none()) {
-        calc_room_after(&robots, &mut by_x, &mut by_y, i);
-        if aligned(&by_x, robots.len(), i) {
-            vertical_band_offset = Some(i);
-        }
-        if aligned(&by_y, robots.len(), i) {
-            horizontal_band_offset = Some(i);
-        }
-        i += 1;
-    }
-    let (
-        Some(horizontal_band_offset),
-        Some(vertical_band_offset),
-    ) = (horizontal_band_offset, vertical_band_offset) else {
-        panic!("didn't find band offsets in expected iterations");
-    };
-    for i in 0..=width {
-        let maybe_overlap = i * width + vertical_band_offset;
-        if (maybe_overlap - horizontal_band_offset) % length == 0 {
-            return maybe_overlap
-        }
-    }
-    panic!("didn't find overlap in expected iterations");
-}
-
-fn calc_position_after(
-    position: isize,
-    velocity: isize,
-    room_size: usize,
-    seconds: usize
-) -> usize {
-    let room_size = isize::try_from(room_size).unwrap();
-    let seconds = isize::try_from(seconds).unwrap();
-    let result = (position + (seconds * velocity) % room_size + room_size) % room_size;
-    usize::try_from(result).unwrap()
-}
-
-fn determine_quadrant(x: usize, y: usize, width: usize, length: usize) -> Quadrant {
-    let half_width = width / 2;
-    let half_length = length / 2;
-    if x < half_width && y < half_length {
-        Quadrant::UpperLeft
-    } else if x < half_width && y > half_length {
-        Quadrant::LowerLeft
-    } else if x > half_width && y < half_length {
-        Quadrant::UpperRight
-    } else if x > half_width && y > half_length {
-        Quadrant::LowerRight
-    } else {
-        Quadrant::None
-    }
+const PUZZLE_ROOM: Dimensions = Dimensions { width: 101, length: 103 };
+
+fn part_1(input: &str) -> u64 {
+    part_1_sized(input, PUZZLE_ROOM.width, PUZZLE_ROOM.length)
 }
 
-fn calc_room_after(robots: &[Robot], by_x: &mut [RobotsAfter], by_y: &mut [RobotsAfter], seconds: usize) {
-    let width = by_x.len();
-    let length = by_y.len();
-    for robot in robots {
-        let x = calc_position_after(robot.position.x, robot.velocity.x, width, seconds);
-        by_x[x] = update_room(&by_x[x], seconds);
-        let y = calc_position_after(robot.position.y, robot.velocity.y, length, seconds);
-        by_y[y] = update_room(&by_y[y], seconds);
-    }
+fn part_1_sized(input: &str, width: usize, length: usize) -> u64 {
+    Swarm::new(parse_input(input), Dimensions { width, length })
+        .frame(100)
+        .safety_factor()
+}
+
+fn part_2(input: &str) -> String {
+    part_2_sized(input, PUZZLE_ROOM.width, PUZZLE_ROOM.length)
+        .map_or_else(|| String::from("no picture found"), |seconds| seconds.to_string())
 }
 
-fn update_room(robots_after: &RobotsAfter, seconds: usize) -> RobotsAfter {
-    if robots_after.seconds == seconds {
-        RobotsAfter { seconds, count: robots_after.count + 1 }
-    } else {
-        RobotsAfter { seconds, count: 1 }
+/// The first time the robots draw a picture, going by all the detectors, or `None` if there
+/// are no robots or they never do.
+fn part_2_sized(input: &str, width: usize, length: usize) -> Option<u64> {
+    Swarm::new(parse_input(input), Dimensions { width, length }).picture(&DETECTORS)
+}
+
+fn export_candidates(input: &str, args: &[String]) -> String {
+    let options = Options::parse(args);
+    let swarm = Swarm::new(parse_input(input), options.dimensions);
+    swarm.rank(&options.detectors, options.top).iter()
+        .map(|candidate| {
+            let frame = swarm.frame(candidate.seconds);
+            format!(
+                "{} seconds: score {:.2}, variance {:.1}, entropy {:.2}, safety factor {}, largest component {}\n",
+                candidate.seconds,
+                candidate.score,
+                frame.variance(),
+                frame.entropy(),
+                frame.safety_factor(),
+                frame.largest_component(),
+            )
+        })
+        .collect()
+}
+
+fn export_image(input: &str, args: &[String]) -> String {
+    let options = Options::parse(args);
+    let swarm = Swarm::new(parse_input(input), options.dimensions);
+    let path = options.out.unwrap_or_else(|| format!("day_14_{}.{}", options.at, options.format.extension()));
+    write_image(&path, &frame_image(&swarm.frame(options.at)).scaled(options.scale), options.format);
+    format!("{} seconds: {}\n", options.at, path)
+}
+
+fn export_frames(input: &str, args: &[String]) -> String {
+    let options = Options::parse(args);
+    let swarm = Swarm::new(parse_input(input), options.dimensions);
+    let dir = options.out.unwrap_or_else(|| String::from("."));
+    fs::create_dir_all(&dir).unwrap();
+    let to = options.to.unwrap_or(options.from + 100);
+    (options.from..to)
+        .map(|seconds| {
+            let path = format!("{}/frame_{:05}.{}", dir, seconds, options.format.extension());
+            write_image(&path, &frame_image(&swarm.frame(seconds)).scaled(options.scale), options.format);
+            format!("{} seconds: {}\n", seconds, path)
+        })
+        .collect()
+}
+
+/// The best candidates' frames side by side, best first, reading left to right and then top to
+/// bottom, in a grey grid.
+fn export_sheet(input: &str, args: &[String]) -> String {
+    const GAP: usize = 2;
+    let options = Options::parse(args);
+    let swarm = Swarm::new(parse_input(input), options.dimensions);
+    let candidates = swarm.rank(&options.detectors, options.top);
+    let tile_width = options.dimensions.width * options.scale;
+    let tile_height = options.dimensions.length * options.scale;
+    let columns = options.columns.min(candidates.len()).max(1);
+    let rows = candidates.len().div_ceil(columns).max(1);
+    let mut sheet = GreyImage::new(
+        columns * (tile_width + GAP) + GAP,
+        rows * (tile_height + GAP) + GAP,
+        128,
+    );
+    let mut output = String::new();
+    for (index, candidate) in candidates.iter().enumerate() {
+        let (column, row) = (index % columns, index / columns);
+        let tile = frame_image(&swarm.frame(candidate.seconds)).scaled(options.scale);
+        sheet.draw(&tile, GAP + column * (tile_width + GAP), GAP + row * (tile_height + GAP));
+        output.push_str(&format!("row {} column {}: {} seconds, score {:.2}\n", row + 1, column + 1, candidate.seconds, candidate.score));
     }
+    let path = options.out.unwrap_or_else(|| format!("day_14_sheet.{}", options.format.extension()));
+    write_image(&path, &sheet, options.format);
+    output.push_str(&format!("written to {}\n", path));
+    output
 }
 
-fn aligned(
-    room: &[RobotsAfter],
-    robot_count: usize,
-    seconds: usize,
-) -> bool {
-    let band_width = room.len() / 3;
-    let mut count = 0;
-    for i in 0..band_width {
-        plus_count(&room[i], seconds, &mut count);
+/// The robots as white pixels on black, brighter where more of them share a tile.
+fn frame_image(frame: &Frame) -> GreyImage {
+    let Dimensions { width, length } = frame.dimensions;
+    let mut counts = vec![0; width * length];
+    for pos in &frame.positions {
+        counts[usize::try_from(pos.y).unwrap() * width + usize::try_from(pos.x).unwrap()] += 1;
     }
-    for i in band_width..room.len() {
-        if is_band(count, robot_count) {
-            return true;
+    let most = counts.iter().copied().max().unwrap_or(0).max(1);
+    let mut image = GreyImage::new(width, length, 0);
+    for (index, &count) in counts.iter().enumerate() {
+        if count > 0 {
+            // a single robot is still clearly visible when some tiles have many
+            let level = 96 + 159 * count / most;
+            image.set(index % width, index / width, u8::try_from(level).unwrap());
         }
-        minus_count(&room[i - band_width], seconds, &mut count);
-        plus_count(&room[i], seconds, &mut count);
     }
-    is_band(count, robot_count)
+    image
 }
 
-fn plus_count(robots_after: &RobotsAfter, seconds: usize, count: &mut usize) {
-    if robots_after.seconds == seconds {
-        *count += robots_after.count;
-    }
+fn write_image(path: &str, image: &GreyImage, format: ImageFormat) {
+    fs::write(path, image.encode(format)).unwrap_or_else(|err| panic!("couldn't write {}: {}", path, err));
+}
+
+/// Options shared by the exports, each of which only looks at the ones it needs.
+struct Options {
+    dimensions: Dimensions,
+    top: usize,
+    detectors: Vec<&'static dyn Detector>,
+    at: u64,
+    from: u64,
+    to: Option<u64>,
+    format: ImageFormat,
+    scale: usize,
+    columns: usize,
+    out: Option<String>,
 }
 
-fn minus_count(robots_after: &RobotsAfter, seconds: usize, count: &mut usize) {
-    if robots_after.seconds == seconds {
-        *count -= robots_after.count;
+impl Options {
+    fn parse(args: &[String]) -> Self {
+        let mut options = Options {
+            dimensions: PUZZLE_ROOM,
+            top: 10,
+            detectors: DETECTORS.to_vec(),
+            at: 0,
+            from: 0,
+            to: None,
+            format: ImageFormat::Png,
+            scale: 1,
+            columns: 5,
+            out: None,
+        };
+        for arg in args {
+            let (key, value) = arg.split_once('=').unwrap_or_else(|| panic!("unknown option: {}", arg));
+            match key {
+                "width" => options.dimensions.width = value.parse().unwrap(),
+                "length" => options.dimensions.length = value.parse().unwrap(),
+                "top" => options.top = value.parse().unwrap(),
+                "detectors" => options.detectors = value.split(',').map(parse_detector).collect(),
+                "at" => options.at = value.parse().unwrap(),
+                "from" => options.from = value.parse().unwrap(),
+                "to" => options.to = Some(value.parse().unwrap()),
+                "format" => options.format = ImageFormat::parse(value),
+                "scale" => options.scale = value.parse().unwrap(),
+                "columns" => options.columns = value.parse().unwrap(),
+                "out" => options.out = Some(value.to_string()),
+                _ => panic!("unknown option: {}", key),
+            }
+        }
+        options
     }
 }
 
-fn is_band(count: usize, robot_count: usize) -> bool {
-    count as f64 > robot_count as f64 * 0.75
+fn parse_detector(name: &str) -> &'static dyn Detector {
+    *DETECTORS.iter()
+        .find(|detector| detector.name() == name)
+        .unwrap_or_else(|| panic!("unknown detector: {}", name))
 }
 
 fn parse_input(input: &str) -> Vec<Robot> {
@@ -174,27 +233,12 @@ fn parse_part(part: &str) -> Point {
     Point { x: x.parse().unwrap(), y: y.parse().unwrap() }
 }
 
-#[derive(Eq, PartialEq, Hash, Copy, Clone)]
-enum Quadrant { UpperLeft, UpperRight, LowerLeft, LowerRight, None }
-
-struct Robot {
-    position: Point,
-    velocity: Point,
-}
-
-#[derive(Clone)]
-struct RobotsAfter {
-    seconds: usize,
-    count: usize,
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::Lcg;
 
-    #[test]
-    fn part_1_sample() {
-        let input = "p=0,4 v=3,-3
+    const SAMPLE: &str = "p=0,4 v=3,-3
 p=6,3 v=-1,-3
 p=10,3 v=-1,2
 p=2,0 v=2,-1
@@ -206,6 +250,140 @@ p=9,3 v=2,3
 p=7,3 v=-1,2
 p=2,4 v=2,-3
 p=9,5 v=-3,-3";
-        assert_eq!(12, part_1_sized(input, 11, 7));
+
+    #[test]
+    fn part_1_sample() {
+        assert_eq!(12, part_1_sized(SAMPLE, 11, 7));
     }
-}
\ No newline at end of file
+
+    /// A swarm that gathers into a solid block at `seconds`, with some stray robots, as input.
+    fn gathering_swarm(dimensions: Dimensions, seconds: i64) -> String {
+        let mut lcg = Lcg::new(12345);
+        let mut random = |range: i64| i64::try_from(lcg.below(range.unsigned_abs())).unwrap();
+        let (width, length) = (dimensions.width as i64, dimensions.length as i64);
+        let mut input = String::new();
+        for robot in 0..120 {
+            let (x, y) = if robot < 80 { (robot % 10, robot / 10) } else { (random(width), random(length)) };
+            let (dx, dy) = (random(11) - 5, random(11) - 5);
+            let start_x = (x - dx * seconds).rem_euclid(width);
+            let start_y = (y - dy * seconds).rem_euclid(length);
+            input.push_str(&format!("p={},{} v={},{}\n", start_x, start_y, dx, dy));
+        }
+        input
+    }
+
+    #[test]
+    fn finds_gathering() {
+        let dimensions = Dimensions { width: 23, length: 19 };
+        let input = gathering_swarm(dimensions, 300);
+        assert_eq!(Some(300), part_2_sized(&input, 23, 19));
+        for detector in DETECTORS {
+            let best = Swarm::new(parse_input(&input), dimensions).rank(&[detector], 1);
+            assert_eq!(300, best[0].seconds, "{} detector", detector.name());
+        }
+        let output = export_candidates(&input, &["width=23", "length=19", "top=2", "detectors=component"].map(String::from));
+        assert!(output.starts_with("300 seconds: "));
+        assert_eq!(2, output.lines().count());
+
+        // the swarm repeats every 437 seconds, and jumping far ahead is as quick as jumping near
+        let swarm = Swarm::new(parse_input(&input), dimensions);
+        assert_eq!(437, swarm.period());
+        assert!(swarm.frame(300).largest_component() >= 80);
+        let frame = swarm.frame(300 + 437 * 1_000_000_000_000);
+        assert_eq!(swarm.frame(300).positions.iter().map(|pos| (pos.x, pos.y)).collect::<Vec<_>>(),
+            frame.positions.iter().map(|pos| (pos.x, pos.y)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn export_images() {
+        let dir = std::env::temp_dir().join(format!("day_14_images_{}", std::process::id()));
+        let dir = dir.to_str().unwrap();
+        let image = |format: &str| {
+            let path = format!("{}/sample.{}", dir, format);
+            let args = ["width=11", "length=7", "at=100", &format!("format={}", format), &format!("out={}", path)];
+            fs::create_dir_all(dir).unwrap();
+            assert_eq!(format!("100 seconds: {}\n", path), export_image(SAMPLE, &args.map(String::from)));
+            fs::read(path).unwrap()
+        };
+
+        // ......2..1.
+        // ...........
+        // 1..........
+        // .11........
+        // .....1.....
+        // ...12......
+        // .1....1....
+        let pbm = image("pbm");
+        assert_eq!(b"P4\n11 7\n", &pbm[..8]);
+        assert_eq!(vec![0x02, 0x40, 0, 0, 0x80, 0, 0x60, 0, 0x04, 0, 0x18, 0, 0x42, 0], pbm[8..]);
+        let pgm = image("pgm");
+        assert_eq!(b"P5\n11 7\n255\n", &pgm[..12]);
+        // two robots are on the brightest tile
+        assert_eq!([0, 255, 0, 0, 175, 0], pgm[12 + 5..12 + 11]);
+        let png = image("png");
+        assert_eq!(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x0b\0\0\0\x07\x08\0\0\0\0", &png[..29]);
+        // a chunk's checksum covers its type, so an empty IEND chunk always ends the same way
+        assert_eq!(b"\0\0\0\0IEND\xae\x42\x60\x82", &png[png.len() - 12..]);
+        // 7 rows of 11 pixels and a filter byte, in one stored deflate block
+        assert_eq!(8 + 25 + 12 + 2 + 5 + 84 + 4 + 12, png.len());
+
+        let output = export_frames(SAMPLE, &["width=11", "length=7", "from=99", "to=101", "format=pbm", &format!("out={}", dir)].map(String::from));
+        assert_eq!(2, output.lines().count());
+        assert_eq!(pbm, fs::read(format!("{}/frame_00100.pbm", dir)).unwrap());
+
+        let input = gathering_swarm(Dimensions { width: 23, length: 19 }, 300);
+        let sheet_path = format!("{}/sheet.pgm", dir);
+        let args = ["width=23", "length=19", "top=3", "columns=2", "scale=2", "format=pgm", &format!("out={}", sheet_path)];
+        let output = export_sheet(&input, &args.map(String::from));
+        assert!(output.starts_with("row 1 column 1: 300 seconds"));
+        assert!(output.contains("row 2 column 1: "));
+        // two 46 pixel wide tiles and two 38 pixel high rows, with a 2 pixel gap around each
+        assert!(fs::read(sheet_path).unwrap().starts_with(b"P5\n98 82\n255\n"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Robots scattered at random, apart from `picture` of them drawing a framed tree in the
+    /// middle of the puzzle room at `seconds`, as input.
+    fn planted_picture(robots: i64, picture: i64, seconds: i64) -> String {
+        let mut lcg = Lcg::new(2024);
+        let mut random = |range: i64| i64::try_from(lcg.below(range.unsigned_abs())).unwrap();
+        let (width, length) = (PUZZLE_ROOM.width as i64, PUZZLE_ROOM.length as i64);
+        let mut tiles = Vec::new();
+        for y in 0..33_i64 {
+            for x in 0..31_i64 {
+                // the frame, and a triangle of tree growing out from its top middle
+                let in_frame = x == 0 || x == 30 || y == 0 || y == 32;
+                let in_tree = (3..27).contains(&y) && (15 - x).abs() < (y - 2) / 2;
+                if in_frame || in_tree {
+                    tiles.push((x + 35, y + 35));
+                }
+            }
+        }
+        let mut input = String::new();
+        for robot in 0..robots {
+            let (x, y) = match tiles.get(robot as usize) {
+                Some(&tile) if robot < picture => tile,
+                _ => (random(width), random(length)),
+            };
+            let (dx, dy) = (random(2 * width - 1) - width + 1, random(2 * length - 1) - length + 1);
+            let start_x = (x - dx * seconds).rem_euclid(width);
+            let start_y = (y - dy * seconds).rem_euclid(length);
+            input.push_str(&format!("p={},{} v={},{}\n", start_x, start_y, dx, dy));
+        }
+        input
+    }
+
+    #[test]
+    fn finds_planted_picture() {
+        let (width, length) = (PUZZLE_ROOM.width, PUZZLE_ROOM.length);
+        assert_eq!(Some(7753), part_2_sized(&planted_picture(500, 250, 7753), width, length));
+        // the frame alone is still a picture, but noise isn't
+        assert_eq!(Some(42), part_2_sized(&planted_picture(500, 120, 42), width, length));
+        assert_eq!("no picture found", part_2(&planted_picture(500, 0, 42)));
+    }
+
+    #[test]
+    fn no_robots() {
+        assert_eq!(None, part_2_sized("", 11, 7));
+    }
+}
@@ -0,0 +1,253 @@
+use crate::common::{Dimensions, Point};
+use num::integer::lcm;
+
+pub struct Robot {
+    pub position: Point,
+    pub velocity: Point,
+}
+
+/// Robots moving in straight lines around a room that wraps at the edges, so where each one is
+/// at any time can be worked out directly, and the whole swarm repeats itself after `period`
+/// seconds.
+pub struct Swarm {
+    robots: Vec<Robot>,
+    dimensions: Dimensions,
+}
+
+impl Swarm {
+    pub fn new(robots: Vec<Robot>, dimensions: Dimensions) -> Self {
+        Swarm { robots, dimensions }
+    }
+
+    pub fn period(&self) -> u64 {
+        u64::try_from(lcm(self.dimensions.width, self.dimensions.length)).unwrap()
+    }
+
+    pub fn frame(&self, seconds: u64) -> Frame {
+        let positions = self.robots.iter()
+            .map(|robot| Point {
+                x: position_after(robot.position.x, robot.velocity.x, self.dimensions.width, seconds),
+                y: position_after(robot.position.y, robot.velocity.y, self.dimensions.length, seconds),
+            })
+            .collect();
+        Frame { positions, dimensions: self.dimensions }
+    }
+
+    /// The times within one period that the detectors think most likely show a pattern, best
+    /// first. Each frame is worked out once and scored by every detector, and each detector's
+    /// scores are normalised to have the same spread before adding them together, so no
+    /// detector outweighs the others just by working in bigger numbers.
+    pub fn rank(&self, detectors: &[&dyn Detector], count: usize) -> Vec<Candidate> {
+        let period = usize::try_from(self.period()).unwrap();
+        let mut scores = vec![Vec::with_capacity(period); detectors.len()];
+        for seconds in 0..self.period() {
+            let frame = self.frame(seconds);
+            for (detector_scores, detector) in scores.iter_mut().zip(detectors) {
+                detector_scores.push(detector.score(&frame));
+            }
+        }
+        let mut totals = vec![0.0; period];
+        for scores in scores {
+            let mean = scores.iter().sum::<f64>() / scores.len() as f64;
+            let spread = (scores.iter().map(|score| (score - mean).powi(2)).sum::<f64>()
+                / scores.len() as f64).sqrt();
+            for (total, score) in totals.iter_mut().zip(scores) {
+                if spread > 0.0 {
+                    *total += (score - mean) / spread;
+                }
+            }
+        }
+        let mut candidates: Vec<Candidate> = totals.into_iter()
+            .enumerate()
+            .map(|(seconds, score)| Candidate { seconds: u64::try_from(seconds).unwrap(), score })
+            .collect();
+        candidates.sort_by(|left, right| right.score.total_cmp(&left.score).then(left.seconds.cmp(&right.seconds)));
+        candidates.truncate(count);
+        candidates
+    }
+
+    /// The time within one period that the robots draw a picture, or `None` if they never do.
+    /// That's the best candidate, as long as the robots in it are joined up enough to be a
+    /// picture, so noise that happens to score best isn't taken for one.
+    pub fn picture(&self, detectors: &[&dyn Detector]) -> Option<u64> {
+        self.rank(detectors, 1).first()
+            .map(|candidate| candidate.seconds)
+            .filter(|&seconds| self.frame(seconds).shows_picture())
+    }
+}
+
+pub struct Candidate {
+    pub seconds: u64,
+    pub score: f64,
+}
+
+/// Where every robot is at one moment.
+pub struct Frame {
+    pub positions: Vec<Point>,
+    pub dimensions: Dimensions,
+}
+
+impl Frame {
+    /// How spread out the robots are: the variance of their x positions plus that of their y
+    /// positions.
+    pub fn variance(&self) -> f64 {
+        if self.positions.is_empty() {
+            return 0.0;
+        }
+        let count = self.positions.len() as f64;
+        let variance = |coords: Vec<f64>| {
+            let mean = coords.iter().sum::<f64>() / count;
+            coords.iter().map(|coord| (coord - mean).powi(2)).sum::<f64>() / count
+        };
+        variance(self.positions.iter().map(|pos| pos.x as f64).collect()) +
+            variance(self.positions.iter().map(|pos| pos.y as f64).collect())
+    }
+
+    /// The Shannon entropy (in bits) of which column the robots are in, plus that of which row,
+    /// which is lower when the robots bunch up into fewer rows and columns.
+    pub fn entropy(&self) -> f64 {
+        let count = self.positions.len() as f64;
+        let entropy = |counts: Vec<usize>| {
+            counts.into_iter()
+                .filter(|&in_line| in_line > 0)
+                .map(|in_line| {
+                    let probability = in_line as f64 / count;
+                    -probability * probability.log2()
+                })
+                .sum::<f64>()
+        };
+        let mut in_cols = vec![0; self.dimensions.width];
+        let mut in_rows = vec![0; self.dimensions.length];
+        for pos in &self.positions {
+            in_cols[usize::try_from(pos.x).unwrap()] += 1;
+            in_rows[usize::try_from(pos.y).unwrap()] += 1;
+        }
+        entropy(in_cols) + entropy(in_rows)
+    }
+
+    /// The number of robots in each quadrant multiplied together, ignoring any robots exactly
+    /// in the middle row or column.
+    pub fn safety_factor(&self) -> u64 {
+        let half_width = isize::try_from(self.dimensions.width / 2).unwrap();
+        let half_length = isize::try_from(self.dimensions.length / 2).unwrap();
+        let mut quadrants = [0; 4];
+        for pos in &self.positions {
+            if pos.x == half_width || pos.y == half_length {
+                continue;
+            }
+            quadrants[usize::from(pos.x > half_width) + 2 * usize::from(pos.y > half_length)] += 1;
+        }
+        quadrants.iter().product()
+    }
+
+    /// The number of robots in the biggest group of robots that are next to each other (not
+    /// counting diagonals), counting robots on the same tile separately.
+    pub fn largest_component(&self) -> usize {
+        let Dimensions { width, length } = self.dimensions;
+        let mut robots_at = vec![0; width * length];
+        for pos in &self.positions {
+            robots_at[self.index(*pos)] += 1;
+        }
+        let mut largest = 0;
+        for &start in &self.positions {
+            if robots_at[self.index(start)] == 0 {
+                continue;
+            }
+            let mut size = 0;
+            let mut pending = vec![start];
+            size += robots_at[self.index(start)];
+            robots_at[self.index(start)] = 0;
+            while let Some(pos) = pending.pop() {
+                for offset in [Point { x: 0, y: -1 }, Point { x: 1, y: 0 }, Point { x: 0, y: 1 }, Point { x: -1, y: 0 }] {
+                    let next = pos + offset;
+                    if next.in_bounds(width, length) && robots_at[self.index(next)] > 0 {
+                        size += robots_at[self.index(next)];
+                        robots_at[self.index(next)] = 0;
+                        pending.push(next);
+                    }
+                }
+            }
+            largest = largest.max(size);
+        }
+        largest
+    }
+
+    /// Whether enough of the robots are joined up for them to be drawing a picture rather than
+    /// scattered at random: at least a tenth of them, which random positions practically never
+    /// manage in a room that's mostly empty.
+    pub fn shows_picture(&self) -> bool {
+        !self.positions.is_empty() && self.largest_component() * 10 >= self.positions.len()
+    }
+
+    fn index(&self, pos: Point) -> usize {
+        usize::try_from(pos.y).unwrap() * self.dimensions.width + usize::try_from(pos.x).unwrap()
+    }
+}
+
+/// Scores how likely a frame is to show a picture rather than noise, higher being more likely.
+pub trait Detector {
+    fn name(&self) -> &'static str;
+
+    fn score(&self, frame: &Frame) -> f64;
+}
+
+/// Robots drawing a picture are bunched together rather than spread around the room.
+pub struct LowVariance;
+
+/// Pictures are made of lines, so the robots are in fewer rows and columns.
+pub struct LowEntropy;
+
+/// A picture (that isn't centred) puts more robots in some quadrants than others.
+pub struct LowSafetyFactor;
+
+/// The robots in a picture are joined up.
+pub struct LargestComponent;
+
+impl Detector for LowVariance {
+    fn name(&self) -> &'static str {
+        "variance"
+    }
+
+    fn score(&self, frame: &Frame) -> f64 {
+        -frame.variance()
+    }
+}
+
+impl Detector for LowEntropy {
+    fn name(&self) -> &'static str {
+        "entropy"
+    }
+
+    fn score(&self, frame: &Frame) -> f64 {
+        -frame.entropy()
+    }
+}
+
+impl Detector for LowSafetyFactor {
+    fn name(&self) -> &'static str {
+        "safety"
+    }
+
+    fn score(&self, frame: &Frame) -> f64 {
+        -(frame.safety_factor() as f64)
+    }
+}
+
+impl Detector for LargestComponent {
+    fn name(&self) -> &'static str {
+        "component"
+    }
+
+    fn score(&self, frame: &Frame) -> f64 {
+        frame.largest_component() as f64
+    }
+}
+
+pub const DETECTORS: [&dyn Detector; 4] = [&LowVariance, &LowEntropy, &LowSafetyFactor, &LargestComponent];
+
+fn position_after(position: isize, velocity: isize, room_size: usize, seconds: u64) -> isize {
+    let room_size = i128::try_from(room_size).unwrap();
+    let position = (i128::try_from(position).unwrap() + i128::try_from(velocity).unwrap() * i128::from(seconds))
+        .rem_euclid(room_size);
+    isize::try_from(position).unwrap()
+}
@@ -1,5 +1,9 @@
-use std::cmp::PartialEq;
-use crate::common::DaySpec;
+mod simulator;
+mod warehouse;
+
+use crate::common::{DaySpec, Dimensions, Export};
+use crate::day_15::simulator::Simulator;
+use crate::day_15::warehouse::{Move, Point, Warehouse, box_coordinate_sum};
 
 pub const DAY_FIFTEEN: DaySpec<u32, u32> = DaySpec {
     day_num: 15,
@@ -7,308 +11,178 @@ pub const DAY_FIFTEEN: DaySpec<u32, u32> = DaySpec {
     part_1,
     part_2_name: "box coordinates sum (big warehouse)",
     part_2,
+    exports: &[
+        Export {
+            name: "frames",
+            description: "the warehouse after each of the given numbers of moves, which can be \
+                single numbers or ranges like 10-20 (default all the moves), with the boxes each \
+                last move shifted; pass \"wide\" for the big warehouse or scale=N for one N \
+                times as wide",
+            run: export_frames,
+            needs_input: true,
+        },
+        Export {
+            name: "robots",
+            description: "runs every move, for any number of robots taking turns in reading \
+                order, and shows how each robot got on; the moves are either shared out between \
+                the robots one at a time, or given for each robot in turn with blank lines \
+                between them; takes the same \"wide\" and scale=N args as frames",
+            run: export_robots,
+            needs_input: true,
+        },
+    ],
 };
 
 fn part_1(input: &str) -> u32 {
     let (warehouse_lines, moves) = parse_input(input);
-    do_part(&mut parse_warehouse(&warehouse_lines), &moves)
+    do_part(parse_warehouse(&warehouse_lines), moves)
 }
 
 fn part_2(input: &str) -> u32 {
     let (warehouse_lines, moves) = parse_input(input);
-    do_part(&mut parse_warehouse_wide(&warehouse_lines), &moves)
-}
-
-fn do_part(warehouse: &mut [Vec<Tile>], moves: &[Move]) -> u32 {
-    let mut robot = find_robot(&warehouse);
-    for mv in moves {
-        robot = do_move(warehouse, robot, *mv);
-    }
-    box_coordinate_sum(warehouse)
-}
-
-fn find_robot(warehouse: &[Vec<Tile>]) -> Point {
-    for (row, tiles) in warehouse.iter().enumerate() {
-        for (col, tile) in tiles.iter().enumerate() {
-            if *tile == Tile::Robot {
-                return Point { x: col, y: row };
-            }
-        }
-    }
-    panic!("couldn't find the robot!");
-}
-
-fn do_move(warehouse: &mut [Vec<Tile>], robot: Point, mv: Move) -> Point {
-    let next_robot = increment(robot, mv);
-    let next_tile = tile_at(warehouse, next_robot);
-    match next_tile {
-        Tile::Empty => do_move_empty(warehouse, robot, mv),
-        Tile::Box => do_move_box(warehouse, robot, mv),
-        Tile::LeftBox | Tile::RightBox => do_move_wide_box(warehouse, robot, mv),
-        _ => robot
-    }
-}
-
-fn do_move_empty(warehouse: &mut [Vec<Tile>], robot: Point, mv: Move) -> Point {
-    let next_robot = increment(robot, mv);
-    set_tile(warehouse, next_robot, Tile::Robot);
-    set_tile(warehouse, robot, Tile::Empty);
-    next_robot
+    do_part(parse_warehouse_wide(&warehouse_lines, 2), moves)
 }
 
-fn do_move_box(warehouse: &mut [Vec<Tile>], robot: Point, mv: Move) -> Point {
-    let next_robot = increment(robot, mv);
-    let mut bx = next_robot;
-    while tile_at(warehouse, bx) == Tile::Box {
-        bx = increment(bx, mv);
-    }
-    if tile_at(warehouse, bx) == Tile::Empty {
-        while bx != next_robot {
-            set_tile(warehouse, bx, Tile::Box);
-            bx = increment(bx, mv.reverse());
-        }
-        set_tile(warehouse, next_robot, Tile::Robot);
-        set_tile(warehouse, robot, Tile::Empty);
-        return next_robot;
-    }
-    robot
-}
-
-fn do_move_wide_box(warehouse: &mut [Vec<Tile>], robot: Point, mv: Move) -> Point {
-    match mv {
-        Move::Left | Move::Right => do_move_wide_box_horizontal(warehouse, robot, mv),
-        Move::Up | Move::Down => do_move_wide_box_vertical(warehouse, robot, mv),
-    }
+fn do_part(warehouse: Warehouse, moves: Vec<Vec<Move>>) -> u32 {
+    let mut simulator = Simulator::new(warehouse, moves);
+    simulator.seek(simulator.move_count());
+    box_coordinate_sum(simulator.warehouse())
 }
 
-fn do_move_wide_box_horizontal(warehouse: &mut [Vec<Tile>], robot: Point, mv: Move) -> Point {
-    let next_robot = increment(robot, mv);
-    let mut bx = next_robot;
-    while let Tile::LeftBox | Tile::RightBox = tile_at(warehouse, bx) {
-        bx = increment(bx, mv);
-    }
-    if tile_at(warehouse, bx) == Tile::Empty {
-        while bx != next_robot {
-            let prev_bx = increment(bx, mv.reverse());
-            set_tile(warehouse, bx, tile_at(warehouse, prev_bx));
-            bx = prev_bx;
-        }
-        set_tile(warehouse, next_robot, Tile::Robot);
-        set_tile(warehouse, robot, Tile::Empty);
-        return next_robot;
-    }
-    robot
-}
-
-fn do_move_wide_box_vertical(warehouse: &mut [Vec<Tile>], robot: Point, mv: Move) -> Point {
-    let next_robot = increment(robot, mv);
-    let (bx_left, bx_right) = {
-        if tile_at(warehouse, next_robot) == Tile::LeftBox {
-            (next_robot, increment(next_robot, Move::Right))
+fn export_frames(input: &str, args: &[String]) -> String {
+    let (warehouse_lines, moves) = parse_input(input);
+    let mut positions = Vec::new();
+    let mut factor = 1;
+    for arg in args {
+        if let Some(arg_factor) = parse_factor(arg) {
+            factor = arg_factor;
+        } else if let Some((start, end)) = arg.split_once('-') {
+            positions.extend(start.parse::<usize>().unwrap()..=end.parse().unwrap());
         } else {
-            (increment(next_robot, Move::Left), next_robot)
+            positions.push(arg.parse().unwrap_or_else(|_| panic!("unknown option: {}", arg)));
         }
-    };
-    if can_move_wide_vertical(warehouse, bx_left, bx_right, mv) {
-        exec_move_wide_vertical(warehouse, bx_left, bx_right, mv);
-        set_tile(warehouse, next_robot, Tile::Robot);
-        set_tile(warehouse, robot, Tile::Empty);
-        return next_robot;
     }
-    robot
-}
-
-fn can_move_wide_vertical(
-    warehouse: &[Vec<Tile>],
-    bx_left: Point,
-    bx_right: Point,
-    mv: Move
-) -> bool {
-    let next_bx_left = increment(bx_left, mv);
-    let next_tile_left = tile_at(warehouse, next_bx_left);
-    let next_bx_right = increment(bx_right, mv);
-    let next_tile_right = tile_at(warehouse, next_bx_right);
-    match (next_tile_left, next_tile_right) {
-        (Tile::Empty, Tile::Empty) => true,
-        (_, Tile::Wall) | (Tile::Wall, _) => false,
-        (Tile::LeftBox, Tile::RightBox) => can_move_wide_vertical(
-            warehouse,
-            next_bx_left,
-            next_bx_right,
-            mv
-        ),
-        _ => {
-            let can_move_left = (next_tile_left == Tile::Empty) ||
-                can_move_wide_vertical(
-                    warehouse,
-                    increment(next_bx_left, Move::Left),
-                    next_bx_left,
-                    mv
-                );
-            let can_move_right = (next_tile_right) == Tile::Empty ||
-                can_move_wide_vertical(
-                    warehouse,
-                    next_bx_right,
-                    increment(next_bx_right, Move::Right),
-                    mv
-                );
-            can_move_left && can_move_right
+    let mut simulator = Simulator::new(parse_warehouse_wide(&warehouse_lines, factor), moves);
+    if positions.is_empty() {
+        positions.push(simulator.move_count());
+    }
+    let mut output = String::new();
+    for position in positions {
+        simulator.seek(position);
+        match simulator.last_step() {
+            Some(step) => {
+                let shifted: Vec<String> = step.shifted.iter()
+                    .map(|shift| format!("{},{}", shift.from.x, shift.from.y))
+                    .collect();
+                let robot = if simulator.robot_count() > 1 { format!("robot {} ", step.robot) } else { String::new() };
+                output.push_str(&format!(
+                    "after {} of {} moves, last {}{} to {},{}, shifting {} boxes{}{}\n",
+                    simulator.position(),
+                    simulator.move_count(),
+                    robot,
+                    step.mv.char(),
+                    step.robot_after.x,
+                    step.robot_after.y,
+                    shifted.len(),
+                    if shifted.is_empty() { "" } else { " from " },
+                    shifted.join(" "),
+                ));
+            }
+            None => output.push_str(&format!("before any of {} moves\n", simulator.move_count())),
         }
+        output.push_str(&warehouse_str(simulator.warehouse()));
     }
+    output
 }
 
-fn exec_move_wide_vertical(warehouse: &mut [Vec<Tile>], bx_left: Point, bx_right: Point, mv: Move) {
-    let next_bx_left = increment(bx_left, mv);
-    let next_tile_left = tile_at(warehouse, next_bx_left);
-    let next_bx_right = increment(bx_right, mv);
-    let next_tile_right = tile_at(warehouse, next_bx_right);
-    if next_tile_left == Tile::LeftBox && next_tile_right == Tile::RightBox {
-        exec_move_wide_vertical(warehouse, next_bx_left, next_bx_right, mv);
-    }
-    if next_tile_left == Tile::RightBox {
-        exec_move_wide_vertical(warehouse, increment(next_bx_left, Move::Left), next_bx_left, mv);
+/// The warehouse's lines, and the robots' moves: either one stream shared between all the
+/// robots, or one stream for each robot with blank lines between them. Blank lines only split
+/// the moves up when they make one stream for each robot, so they're otherwise ignored.
+fn export_robots(input: &str, args: &[String]) -> String {
+    let (warehouse_lines, moves) = parse_input(input);
+    let mut factor = 1;
+    for arg in args {
+        factor = parse_factor(arg).unwrap_or_else(|| panic!("unknown option: {}", arg));
     }
-    if next_tile_right == Tile::LeftBox {
-        exec_move_wide_vertical(warehouse, next_bx_right, increment(next_bx_right, Move::Right), mv);
+    let mut simulator = Simulator::new(parse_warehouse_wide(&warehouse_lines, factor), moves);
+    simulator.seek(simulator.move_count());
+    let mut output = String::new();
+    for (robot, stats) in simulator.stats().iter().enumerate() {
+        output.push_str(&format!(
+            "robot {} at {},{}: {} moves, {} blocked, {} boxes pushed\n",
+            robot,
+            stats.position.x,
+            stats.position.y,
+            stats.moves,
+            stats.blocked,
+            stats.boxes_pushed,
+        ));
     }
-    set_tile(warehouse, next_bx_left, Tile::LeftBox);
-    set_tile(warehouse, next_bx_right, Tile::RightBox);
-    set_tile(warehouse, bx_left, Tile::Empty);
-    set_tile(warehouse, bx_right, Tile::Empty);
-}
-
-fn increment(robot: Point, mv: Move) -> Point {
-    match mv {
-        Move::Up => Point { x: robot.x, y: robot.y - 1 },
-        Move::Down => Point { x: robot.x, y: robot.y + 1 },
-        Move::Left => Point { x: robot.x - 1, y: robot.y },
-        Move::Right => Point { x: robot.x + 1, y: robot.y },
-    }
-}
-
-fn tile_at(warehouse: &[Vec<Tile>], point: Point) -> Tile {
-    warehouse[point.y][point.x]
-}
-
-fn set_tile(warehouse: &mut [Vec<Tile>], point: Point, tile: Tile) {
-    warehouse[point.y][point.x] = tile;
+    output.push_str(&format!("box coordinates sum {}\n", box_coordinate_sum(simulator.warehouse())));
+    output
 }
 
-fn box_coordinate_sum(warehouse: &[Vec<Tile>]) -> u32 {
-    warehouse.iter().enumerate()
-        .flat_map(|(row, tiles)| {
-            tiles.iter().enumerate()
-                .filter(|(_, tile)| **tile == Tile::Box || **tile == Tile::LeftBox)
-                .map(move |(col, _)| ((100 * row) + col) as u32)
+/// How many times wider than the map the warehouse should be, from "wide" or scale=N.
+fn parse_factor(arg: &str) -> Option<usize> {
+    if arg == "wide" {
+        Some(2)
+    } else {
+        arg.strip_prefix("scale=").map(|factor| match factor.parse().unwrap() {
+            0 => panic!("scale has to be at least 1, as every tile needs some width"),
+            factor => factor,
         })
-        .sum()
+    }
 }
 
-fn parse_input(input: &str) -> (Vec<&str>, Vec<Move>) {
+fn parse_input(input: &str) -> (Vec<&str>, Vec<Vec<Move>>) {
     let mut lines = input.lines();
     let warehouse_lines: Vec<&str> = lines.by_ref()
         .take_while(|&line| !line.is_empty())
         .collect();
-    let moves: Vec<Move> = lines.flat_map(|line| {
-        line.chars()
-            .map(|c| Move::parse(c))
-    })
-        .collect();
-    (warehouse_lines, moves)
+    let mut streams = vec![Vec::new()];
+    for line in lines {
+        if line.is_empty() {
+            streams.push(Vec::new());
+        } else {
+            streams.last_mut().unwrap().extend(line.chars().map(Move::parse));
+        }
+    }
+    streams.retain(|stream| !stream.is_empty());
+    (warehouse_lines, streams)
 }
 
-fn parse_warehouse(warehouse_lines: &[&str]) -> Vec<Vec<Tile>> {
-    warehouse_lines.iter()
-        .map(|&line| {
-            line.chars()
-                .map(Tile::parse)
-                .collect()
-        })
-        .collect()
+fn parse_warehouse(warehouse_lines: &[&str]) -> Warehouse {
+    Warehouse::parse(warehouse_lines)
 }
 
-fn parse_warehouse_wide(warehouse_lines: &[&str]) -> Vec<Vec<Tile>> {
-    warehouse_lines.iter()
+/// The warehouse with everything in it `factor` times as wide, apart from the robot.
+fn parse_warehouse_wide(warehouse_lines: &[&str], factor: usize) -> Warehouse {
+    let wide_lines: Vec<String> = warehouse_lines.iter()
         .map(|&line| {
             line.chars()
-                .flat_map(Tile::parse_wide)
+                .map(|c| match c {
+                    'O' if factor > 1 => format!("[{}]", "-".repeat(factor - 2)),
+                    '@' => format!("@{}", ".".repeat(factor - 1)),
+                    _ => c.to_string().repeat(factor),
+                })
                 .collect()
         })
-        .collect()
+        .collect();
+    Warehouse::parse(&wide_lines.iter().map(String::as_str).collect::<Vec<&str>>())
 }
 
-fn warehouse_str(warehouse: &[Vec<Tile>]) -> String {
+fn warehouse_str(warehouse: &Warehouse) -> String {
+    let Dimensions { width, length } = warehouse.dimensions();
     let mut s = String::new();
-    for row in warehouse {
-        for tile in row {
-            s.push({
-                match tile {
-                    Tile::Box => 'O',
-                    Tile::Wall => '#',
-                    Tile::Robot => '@',
-                    Tile::Empty => '.',
-                    Tile::LeftBox => '[',
-                    Tile::RightBox => ']',
-                }
-            });
+    for y in 0..length {
+        for x in 0..width {
+            s.push(warehouse.glyph(Point { x, y }));
         }
         s.push('\n');
     }
     s
 }
 
-#[derive(Copy, Clone)]
-enum Move { Up, Down, Left, Right }
-
-impl Move {
-    fn parse(c: char) -> Self {
-        match c {
-            '^' => Move::Up,
-            'v' => Move::Down,
-            '<' => Move::Left,
-            '>' => Move::Right,
-            _ => panic!("unexpected move char: {}", c),
-        }
-    }
-
-    fn reverse(&self) -> Self {
-        match self {
-            Move::Up => Move::Down,
-            Move::Down => Move::Up,
-            Move::Left => Move::Right,
-            Move::Right => Move::Left,
-        }
-    }
-}
-
-#[derive(Eq, PartialEq, Copy, Clone)]
-enum Tile { Box, Wall, Robot, Empty, LeftBox, RightBox }
-
-impl Tile {
-    fn parse(c: char) -> Self {
-        match c {
-            'O' => Tile::Box,
-            '#' => Tile::Wall,
-            '@' => Tile::Robot,
-            '.' => Tile::Empty,
-            _ => panic!("unexpected tile char: {}", c),
-        }
-    }
-
-    fn parse_wide(c: char) -> [Self; 2] {
-        match c {
-            'O' => [Tile::LeftBox, Tile::RightBox],
-            '#' => [Tile::Wall, Tile::Wall],
-            '@' => [Tile::Robot, Tile::Empty],
-            '.' => [Tile::Empty, Tile::Empty],
-            _ => panic!("unexpected double tile char: {}", c),
-        }
-    }
-}
-
-#[derive(Eq, PartialEq, Copy, Clone)]
-struct Point { x: usize, y: usize }
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -352,8 +226,151 @@ v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^";
         assert_eq!(10092, part_1(INPUT_LARGE));
     }
 
+    #[test]
+    fn blank_lines_in_moves() {
+        // one robot, so the moves are one stream whatever blank lines there are
+        let input = INPUT_SMALL.replace("<^^>>>", "<^^\n\n>>>\n") + "\n\n";
+        assert_eq!(2028, part_1(&input));
+        let (warehouse, moves) = INPUT_LARGE.split_once("\n\n").unwrap();
+        assert_eq!(10092, part_1(&format!("{}\n\n{}", warehouse, moves.replace('\n', "\n\n"))));
+
+        // two robots but three streams, so the moves are shared out as if it were one
+        let warehouse = "#########\n#.......#\n#.@.O.@.#\n#.......#\n#########";
+        assert_eq!(
+            export_robots(&format!("{}\n\n><><v<", warehouse), &[]),
+            export_robots(&format!("{}\n\n><\n\n><\n\nv<", warehouse), &[]),
+        );
+    }
+
     #[test]
     fn part_2_sample() {
         assert_eq!(9021, part_2(INPUT_LARGE));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn step_undo_and_seek() {
+        let output = export_frames(INPUT_SMALL, &["3-5".to_string()]);
+        assert_eq!("after 3 of 15 moves, last ^ to 2,1, shifting 0 boxes
+########
+#.@O.O.#
+##..O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+after 4 of 15 moves, last > to 3,1, shifting 1 boxes from 3,1
+########
+#..@OO.#
+##..O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+after 5 of 15 moves, last > to 4,1, shifting 2 boxes from 4,1 5,1
+########
+#...@OO#
+##..O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+", output);
+
+        // going back and forth between any two points matches going straight there
+        let (warehouse_lines, moves) = parse_input(INPUT_LARGE);
+        let frame_at = |position| {
+            let mut simulator = Simulator::new(parse_warehouse_wide(&warehouse_lines, 2), moves.clone());
+            simulator.seek(position);
+            warehouse_str(simulator.warehouse())
+        };
+        let mut simulator = Simulator::new(parse_warehouse_wide(&warehouse_lines, 2), moves.clone());
+        let start = warehouse_str(simulator.warehouse());
+        for position in [700, 312, 313, 0, 699, 1] {
+            simulator.seek(position);
+            assert_eq!(frame_at(position), warehouse_str(simulator.warehouse()));
+        }
+        simulator.seek(0);
+        assert_eq!(start, warehouse_str(simulator.warehouse()));
+        simulator.seek(simulator.move_count());
+        assert_eq!(9021, box_coordinate_sum(simulator.warehouse()));
+        assert!(simulator.step().is_none());
+        while simulator.undo().is_some() {}
+        assert_eq!(start, warehouse_str(simulator.warehouse()));
+    }
+
+    #[test]
+    fn box_shapes() {
+        // an L shaped box and a tall box
+        let input = "########
+#......#
+#.AAB..#
+#.A.B..#
+#..@...#
+########
+
+^^^>>>";
+        let output = export_frames(input, &[String::from("6")]);
+        assert_eq!("after 6 of 6 moves, last > to 5,2, shifting 0 boxes
+########
+#.AA...#
+#.A..@B#
+#.....B#
+#......#
+########
+", output);
+        assert_eq!(102 + 206, part_1(input));
+
+        // boxes three tiles wide, with one pushing another on one side only
+        let input = "######
+#....#
+#.OO.#
+#..O.#
+#..@.#
+######
+
+^^<^";
+        let (warehouse_lines, moves) = parse_input(input);
+        let mut simulator = Simulator::new(parse_warehouse_wide(&warehouse_lines, 3), moves);
+        simulator.seek(4);
+        assert_eq!("##################
+###...[-][-]...###
+###.....@[-]...###
+###............###
+###............###
+##################
+", warehouse_str(simulator.warehouse()));
+        assert_eq!(106 + 109 + 209, box_coordinate_sum(simulator.warehouse()));
+    }
+
+    #[test]
+    fn several_robots() {
+        let warehouse = "#########
+#.......#
+#.@.O.@.#
+#.......#
+#########";
+        // each robot is stopped by the other one being behind the box
+        let output = export_robots(&format!("{}\n\n><><v<", warehouse), &[]);
+        assert_eq!("robot 0 at 3,3: 3 moves, 1 blocked, 0 boxes pushed
+robot 1 at 4,2: 3 moves, 1 blocked, 1 boxes pushed
+box coordinates sum 203
+", output);
+        let output = export_robots(&format!("{}\n\n>>v\n\n<<<<", warehouse), &[]);
+        assert_eq!("robot 0 at 3,3: 3 moves, 1 blocked, 0 boxes pushed
+robot 1 at 3,2: 4 moves, 1 blocked, 2 boxes pushed
+box coordinates sum 202
+", output);
+        let output = export_frames(&format!("{}\n\n>>v\n\n<<<<", warehouse), &[String::from("3")]);
+        assert!(output.starts_with("after 3 of 7 moves, last robot 0 > to 3,2, shifting 0 boxes\n"));
+
+        // robots block each other directly too
+        let output = export_robots("######\n#@@..#\n######\n\n>>>", &[]);
+        assert_eq!("robot 0 at 2,1: 2 moves, 1 blocked, 0 boxes pushed
+robot 1 at 3,1: 1 moves, 0 blocked, 0 boxes pushed
+box coordinates sum 0
+", output);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::day_15::warehouse::{Change, Move, Point, Shift, Warehouse, do_move, find_robots};
+
+/// One move that has been made, with everything needed to take it back.
+pub struct Step {
+    pub robot: usize,
+    pub mv: Move,
+    pub robot_before: Point,
+    pub robot_after: Point,
+    pub changes: Vec<Change>,
+    pub shifted: Vec<Shift>,
+}
+
+/// How one robot has got on so far.
+pub struct RobotStats {
+    pub position: Point,
+    pub moves: usize,
+    pub blocked: usize,
+    pub boxes_pushed: usize,
+}
+
+/// Runs the robots' moves one at a time, keeping every move made so that they can be undone
+/// and the warehouse can be shown as it was after any number of moves.
+///
+/// Robots are numbered in reading order, and take turns in that order. When there's one
+/// stream of moves per robot, robots whose moves have run out skip their turns. Otherwise the
+/// streams are joined into one, which is shared out between the robots a move at a time.
+/// Robots block each other, and can't push boxes into each other.
+pub struct Simulator {
+    warehouse: Warehouse,
+    robots: Vec<Point>,
+    turns: Vec<(usize, Move)>,
+    history: Vec<Step>,
+}
+
+impl Simulator {
+    pub fn new(warehouse: Warehouse, streams: Vec<Vec<Move>>) -> Self {
+        let robots = find_robots(&warehouse);
+        let turns = if streams.len() == robots.len() {
+            let longest = streams.iter().map(Vec::len).max().unwrap_or(0);
+            (0..longest)
+                .flat_map(|turn| {
+                    streams.iter()
+                        .enumerate()
+                        .filter_map(move |(robot, moves)| moves.get(turn).map(|&mv| (robot, mv)))
+                })
+                .collect()
+        } else {
+            streams.concat().into_iter()
+                .enumerate()
+                .map(|(turn, mv)| (turn % robots.len(), mv))
+                .collect()
+        };
+        Simulator { warehouse, robots, turns, history: Vec::new() }
+    }
+
+    pub fn warehouse(&self) -> &Warehouse {
+        &self.warehouse
+    }
+
+    pub fn robot_count(&self) -> usize {
+        self.robots.len()
+    }
+
+    /// How many moves have been made.
+    pub fn position(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn move_count(&self) -> usize {
+        self.turns.len()
+    }
+
+    pub fn last_step(&self) -> Option<&Step> {
+        self.history.last()
+    }
+
+    /// Makes the next move, if there are any left.
+    pub fn step(&mut self) -> Option<&Step> {
+        let &(robot, mv) = self.turns.get(self.history.len())?;
+        let robot_before = self.robots[robot];
+        let robot_after = do_move(&mut self.warehouse, robot_before, mv);
+        self.robots[robot] = robot_after;
+        let (changes, shifted) = self.warehouse.take_record();
+        self.history.push(Step { robot, mv, robot_before, robot_after, changes, shifted });
+        self.history.last()
+    }
+
+    /// Takes back the last move made, if any have been.
+    pub fn undo(&mut self) -> Option<Step> {
+        let step = self.history.pop()?;
+        self.warehouse.revert(&step.changes, &step.shifted);
+        self.robots[step.robot] = step.robot_before;
+        Some(step)
+    }
+
+    /// Steps forwards or backwards until exactly `position` moves have been made.
+    pub fn seek(&mut self, position: usize) {
+        assert!(position <= self.turns.len(), "can't seek to move {} of {}", position, self.turns.len());
+        while self.history.len() < position {
+            self.step();
+        }
+        while self.history.len() > position {
+            self.undo();
+        }
+    }
+
+    /// Each robot's moves so far, counting blocked moves as moves.
+    pub fn stats(&self) -> Vec<RobotStats> {
+        let mut stats: Vec<RobotStats> = self.robots.iter()
+            .map(|&position| RobotStats { position, moves: 0, blocked: 0, boxes_pushed: 0 })
+            .collect();
+        for step in &self.history {
+            let robot_stats = &mut stats[step.robot];
+            robot_stats.moves += 1;
+            if step.robot_after == step.robot_before {
+                robot_stats.blocked += 1;
+            }
+            robot_stats.boxes_pushed += step.shifted.len();
+        }
+        stats
+    }
+}
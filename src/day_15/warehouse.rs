@@ -0,0 +1,303 @@
+use crate::common::Dimensions;
+use std::mem;
+
+/// The warehouse's tiles and boxes, along with a record of what the move in progress has
+/// changed.
+pub struct Warehouse {
+    tiles: Vec<Vec<Tile>>,
+    boxes: Vec<WarehouseBox>,
+    changes: Vec<Change>,
+    shifted: Vec<Shift>,
+}
+
+/// A box of any shape, made of the cells at `cells` offsets from `position`, the top left
+/// corner of the box's bounding box, each cell with the character it's drawn with.
+struct WarehouseBox {
+    position: Point,
+    cells: Vec<(Point, char)>,
+}
+
+/// A tile that a move changed, and what it was before.
+#[derive(Copy, Clone)]
+pub struct Change {
+    pub point: Point,
+    pub before: Tile,
+}
+
+/// A box that a move pushed, and where it was before.
+#[derive(Copy, Clone)]
+pub struct Shift {
+    pub id: usize,
+    pub from: Point,
+}
+
+impl Warehouse {
+    /// Reads a map where `#` is a wall, `@` a robot and `.` empty floor. Boxes can be `O` for a
+    /// single tile, `[` and `]` with any number of `-` between them for a wide box, or any other
+    /// letter, where tiles with the same letter next to each other make up one box.
+    pub fn parse(warehouse_lines: &[&str]) -> Self {
+        let chars: Vec<Vec<char>> = warehouse_lines.iter()
+            .map(|line| line.chars().collect())
+            .collect();
+        let mut tiles: Vec<Vec<Tile>> = chars.iter()
+            .map(|row| vec![Tile::Empty; row.len()])
+            .collect();
+        let mut boxes = Vec::new();
+        for (y, row) in chars.iter().enumerate() {
+            for (x, &c) in row.iter().enumerate() {
+                let cells = match c {
+                    '#' => {
+                        tiles[y][x] = Tile::Wall;
+                        continue;
+                    }
+                    '@' => {
+                        tiles[y][x] = Tile::Robot;
+                        continue;
+                    }
+                    '.' | '-' | ']' => continue,
+                    'O' => vec![(Point { x, y }, c)],
+                    '[' => {
+                        let end = (x + 1..row.len())
+                            .find(|&end| row[end] != '-')
+                            .filter(|&end| row[end] == ']')
+                            .unwrap_or_else(|| panic!("unclosed box at {},{}", x, y));
+                        (x..=end).map(|x| (Point { x, y }, row[x])).collect()
+                    }
+                    _ if c.is_ascii_alphabetic() => {
+                        if tiles[y][x] != Tile::Empty {
+                            continue;
+                        }
+                        same_letter(&chars, Point { x, y })
+                    }
+                    _ => panic!("unexpected tile char: {}", c),
+                };
+                let id = boxes.len();
+                for (cell, _) in &cells {
+                    tiles[cell.y][cell.x] = Tile::Box(id);
+                }
+                let position = Point {
+                    x: cells.iter().map(|(cell, _)| cell.x).min().unwrap(),
+                    y: cells.iter().map(|(cell, _)| cell.y).min().unwrap(),
+                };
+                let cells = cells.into_iter()
+                    .map(|(cell, c)| (Point { x: cell.x - position.x, y: cell.y - position.y }, c))
+                    .collect();
+                boxes.push(WarehouseBox { position, cells });
+            }
+        }
+        Warehouse { tiles, boxes, changes: Vec::new(), shifted: Vec::new() }
+    }
+
+    pub fn dimensions(&self) -> Dimensions {
+        Dimensions { width: self.tiles.first().map_or(0, |row| row.len()), length: self.tiles.len() }
+    }
+
+    /// The character the tile at `point` is drawn with.
+    pub fn glyph(&self, point: Point) -> char {
+        match tile_at(self, point) {
+            Tile::Wall => '#',
+            Tile::Robot => '@',
+            Tile::Empty => '.',
+            Tile::Box(id) => {
+                let bx = &self.boxes[id];
+                let offset = Point { x: point.x - bx.position.x, y: point.y - bx.position.y };
+                bx.cells.iter()
+                    .find(|(cell, _)| *cell == offset)
+                    .map(|&(_, c)| c)
+                    .unwrap()
+            }
+        }
+    }
+
+    /// The tiles changed and the boxes shifted since this was last called.
+    pub fn take_record(&mut self) -> (Vec<Change>, Vec<Shift>) {
+        let mut shifted = mem::take(&mut self.shifted);
+        shifted.sort_by_key(|shift| (shift.from.y, shift.from.x));
+        (mem::take(&mut self.changes), shifted)
+    }
+
+    /// Puts back the tiles and boxes as they were before the changes and shifts, without
+    /// recording anything.
+    pub fn revert(&mut self, changes: &[Change], shifted: &[Shift]) {
+        for change in changes.iter().rev() {
+            self.tiles[change.point.y][change.point.x] = change.before;
+        }
+        for shift in shifted {
+            self.boxes[shift.id].position = shift.from;
+        }
+    }
+
+    fn cells(&self, id: usize) -> impl Iterator<Item = Point> + '_ {
+        let position = self.boxes[id].position;
+        self.boxes[id].cells.iter()
+            .map(move |(offset, _)| Point { x: position.x + offset.x, y: position.y + offset.y })
+    }
+}
+
+/// Every tile with the same letter as `start` that can be reached from it without leaving
+/// that letter.
+fn same_letter(chars: &[Vec<char>], start: Point) -> Vec<(Point, char)> {
+    let letter = chars[start.y][start.x];
+    let mut cells = vec![(start, letter)];
+    let mut next = 0;
+    while next < cells.len() {
+        let (cell, _) = cells[next];
+        next += 1;
+        for mv in [Move::Up, Move::Down, Move::Left, Move::Right] {
+            let Some(neighbour) = checked_increment(cell, mv) else {
+                continue;
+            };
+            let is_letter = chars.get(neighbour.y)
+                .and_then(|row| row.get(neighbour.x))
+                .is_some_and(|&c| c == letter);
+            if is_letter && !cells.iter().any(|&(other, _)| other == neighbour) {
+                cells.push((neighbour, letter));
+            }
+        }
+    }
+    cells
+}
+
+/// Where every robot is, in reading order.
+pub fn find_robots(warehouse: &Warehouse) -> Vec<Point> {
+    let robots: Vec<Point> = warehouse.tiles.iter()
+        .enumerate()
+        .flat_map(|(row, tiles)| {
+            tiles.iter()
+                .enumerate()
+                .filter(|(_, tile)| **tile == Tile::Robot)
+                .map(move |(col, _)| Point { x: col, y: row })
+        })
+        .collect();
+    assert!(!robots.is_empty(), "couldn't find the robot!");
+    robots
+}
+
+pub fn do_move(warehouse: &mut Warehouse, robot: Point, mv: Move) -> Point {
+    let next_robot = increment(robot, mv);
+    match tile_at(warehouse, next_robot) {
+        Tile::Empty => {}
+        Tile::Box(id) => {
+            let Some(pushed) = pushed_boxes(warehouse, id, mv) else {
+                return robot;
+            };
+            shift_boxes(warehouse, &pushed, mv);
+        }
+        Tile::Wall | Tile::Robot => return robot,
+    }
+    set_tile(warehouse, next_robot, Tile::Robot);
+    set_tile(warehouse, robot, Tile::Empty);
+    next_robot
+}
+
+/// Every box that would move when pushing the box `first`, found with a breadth first search
+/// over the boxes in front of the boxes already found, or `None` if any of them would be
+/// pushed into a wall or a robot.
+fn pushed_boxes(warehouse: &Warehouse, first: usize, mv: Move) -> Option<Vec<usize>> {
+    let mut pushed = vec![first];
+    let mut is_pushed = vec![false; warehouse.boxes.len()];
+    is_pushed[first] = true;
+    let mut next = 0;
+    while next < pushed.len() {
+        let id = pushed[next];
+        next += 1;
+        for cell in warehouse.cells(id) {
+            match tile_at(warehouse, increment(cell, mv)) {
+                Tile::Wall | Tile::Robot => return None,
+                Tile::Box(other) if !is_pushed[other] => {
+                    is_pushed[other] = true;
+                    pushed.push(other);
+                }
+                _ => {}
+            }
+        }
+    }
+    Some(pushed)
+}
+
+/// Moves all the boxes one tile, clearing them all before putting them back so that boxes
+/// moving into each other's old tiles don't get in each other's way.
+fn shift_boxes(warehouse: &mut Warehouse, pushed: &[usize], mv: Move) {
+    for &id in pushed {
+        let cells: Vec<Point> = warehouse.cells(id).collect();
+        for cell in cells {
+            set_tile(warehouse, cell, Tile::Empty);
+        }
+    }
+    for &id in pushed {
+        let from = warehouse.boxes[id].position;
+        warehouse.shifted.push(Shift { id, from });
+        warehouse.boxes[id].position = increment(from, mv);
+        let cells: Vec<Point> = warehouse.cells(id).collect();
+        for cell in cells {
+            set_tile(warehouse, cell, Tile::Box(id));
+        }
+    }
+}
+
+fn increment(robot: Point, mv: Move) -> Point {
+    match mv {
+        Move::Up => Point { x: robot.x, y: robot.y - 1 },
+        Move::Down => Point { x: robot.x, y: robot.y + 1 },
+        Move::Left => Point { x: robot.x - 1, y: robot.y },
+        Move::Right => Point { x: robot.x + 1, y: robot.y },
+    }
+}
+
+fn checked_increment(point: Point, mv: Move) -> Option<Point> {
+    match mv {
+        Move::Up => Some(Point { x: point.x, y: point.y.checked_sub(1)? }),
+        Move::Left => Some(Point { x: point.x.checked_sub(1)?, y: point.y }),
+        _ => Some(increment(point, mv)),
+    }
+}
+
+fn tile_at(warehouse: &Warehouse, point: Point) -> Tile {
+    warehouse.tiles[point.y][point.x]
+}
+
+fn set_tile(warehouse: &mut Warehouse, point: Point, tile: Tile) {
+    let before = warehouse.tiles[point.y][point.x];
+    if before != tile {
+        warehouse.changes.push(Change { point, before });
+        warehouse.tiles[point.y][point.x] = tile;
+    }
+}
+
+/// The sum of each box's distance from the top edge times 100 plus its distance from the left
+/// edge, measured to the nearest tile of the box.
+pub fn box_coordinate_sum(warehouse: &Warehouse) -> u32 {
+    warehouse.boxes.iter()
+        .map(|bx| (100 * bx.position.y + bx.position.x) as u32)
+        .sum()
+}
+
+#[derive(Copy, Clone)]
+pub enum Move { Up, Down, Left, Right }
+
+impl Move {
+    pub fn parse(c: char) -> Self {
+        match c {
+            '^' => Move::Up,
+            'v' => Move::Down,
+            '<' => Move::Left,
+            '>' => Move::Right,
+            _ => panic!("unexpected move char: {}", c),
+        }
+    }
+
+    pub fn char(&self) -> char {
+        match self {
+            Move::Up => '^',
+            Move::Down => 'v',
+            Move::Left => '<',
+            Move::Right => '>',
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Copy, Clone)]
+pub enum Tile { Wall, Robot, Empty, Box(usize) }
+
+#[derive(Eq, PartialEq, Copy, Clone)]
+pub struct Point { pub x: usize, pub y: usize }
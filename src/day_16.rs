@@ -1,29 +1,113 @@
 mod maze;
 
-use crate::common::DaySpec;
-use crate::day_16::maze::Maze;
+use crate::common::{DaySpec, Direction, Export};
+use crate::day_16::maze::{CostModel, Maze};
 
-pub const DAY_SIXTEEN: DaySpec<u32, usize> = DaySpec {
+pub const DAY_SIXTEEN: DaySpec<u64, usize> = DaySpec {
     day_num: 16,
     part_1_name: "lowest score possible",
     part_1,
     part_2_name: "tiles on best paths",
     part_2,
+    exports: &[
+        Export {
+            name: "dot",
+            description: "maze state graph as a Graphviz graph, with best-path edges highlighted; \
+                takes the same args as score",
+            run: export_dot,
+            needs_input: true,
+        },
+        Export {
+            name: "path",
+            description: "the maze with one of the best paths drawn on in arrows and the other \
+                tiles on best paths as O, then how many moves and turns it takes and how many \
+                best paths there are; takes the same args as score",
+            run: export_path,
+            needs_input: true,
+        },
+        Export {
+            name: "score",
+            description: "the lowest score and the tiles on best paths, from any S to any E; \
+                optional args are turn=N, step=N and reverse=N move costs (default 1000, 1 and \
+                two turns) and start=N|E|S|W (default E)",
+            run: export_score,
+            needs_input: true,
+        },
+    ],
 };
 
-fn part_1(input: &str) -> u32 {
-    Maze::parse_and_traverse(input)
+fn part_1(input: &str) -> u64 {
+    Maze::parse_and_traverse(input, Direction::East, &CostModel::PUZZLE)
         .min_score()
+        .expect("the end should be reachable")
 }
 
 fn part_2(input: &str) -> usize {
-    Maze::parse_and_traverse(input)
+    Maze::parse_and_traverse(input, Direction::East, &CostModel::PUZZLE)
         .best_seats_count()
 }
 
+fn export_dot(input: &str, args: &[String]) -> String {
+    parse_args_and_traverse(input, args)
+        .to_dot()
+}
+
+fn export_score(input: &str, args: &[String]) -> String {
+    let maze = parse_args_and_traverse(input, args);
+    match maze.min_score() {
+        Some(score) => format!("lowest score {}, {} tiles on best paths\n", score, maze.best_seats_count()),
+        None => String::from("no way to reach the end\n"),
+    }
+}
+
+fn export_path(input: &str, args: &[String]) -> String {
+    let maze = parse_args_and_traverse(input, args);
+    let Some(best_path) = maze.best_path() else {
+        return String::from("no way to reach the end\n");
+    };
+    let path_count = maze.best_path_count()
+        .map_or(String::from("infinitely many"), |count| count.to_string());
+    format!(
+        "{}score {}, {} moves, {} turns, {} best paths, {} tiles on best paths\n",
+        maze.render_best_path(),
+        best_path.score,
+        best_path.moves.len() - 1,
+        best_path.turns,
+        path_count,
+        maze.best_seats_count(),
+    )
+}
+
+/// The maze traversed with args turn=N, step=N and reverse=N for the costs of each move (by
+/// default 1000, 1, and two turns' worth), and start=N|E|S|W for the direction to start in.
+fn parse_args_and_traverse(input: &str, args: &[String]) -> Maze {
+    let mut costs = CostModel::PUZZLE;
+    let mut start_direction = Direction::East;
+    for arg in args {
+        let (key, value) = arg.split_once('=').unwrap_or_else(|| panic!("unknown option: {}", arg));
+        match key {
+            "turn" => costs.turn = value.parse().unwrap(),
+            "step" => costs.step = value.parse().unwrap(),
+            "reverse" => costs.reverse = Some(value.parse().unwrap()),
+            "start" => start_direction = match value {
+                "N" => Direction::North,
+                "E" => Direction::East,
+                "S" => Direction::South,
+                "W" => Direction::West,
+                _ => panic!("unknown direction: {}", value),
+            },
+            _ => panic!("unknown option: {}", key),
+        }
+    }
+    Maze::parse_and_traverse(input, start_direction, &costs)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use num::BigUint;
+    use crate::test_support::Lcg;
+    use std::time::Instant;
 
     const INPUT_SMALL: &str = "###############
 #.......#....E#
@@ -69,4 +153,116 @@ mod tests {
         assert_eq!(45, part_2(INPUT_SMALL));
         assert_eq!(64, part_2(INPUT_LARGE));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn export_dot_sample() {
+        let dot = export_dot(INPUT_SMALL, &[]);
+        assert!(dot.starts_with("digraph \"maze\" {"));
+        assert!(dot.contains("\"13,1,N\" -> \"12,1,N\" [label=\"1\", color=\"red\""));
+        assert!(dot.contains("\"13,1,E\" -> \"13,1,S\" [label=\"1000\"];"));
+    }
+
+    #[test]
+    fn starts_ends_and_costs() {
+        // the start and end can be anywhere, even on the edge
+        let maze = "#####E#
+#.....#
+#.###.#
+#S....#
+#######";
+        assert_eq!(1007, part_1(maze));
+        assert_eq!("lowest score 2007, 8 tiles on best paths\n", export_score(maze, &[String::from("turn=2000")]));
+        assert_eq!("lowest score 15, 8 tiles on best paths\n", export_score(maze, &[String::from("turn=1"), String::from("step=2")]));
+        // scores can go past what fits in 32 bits
+        assert_eq!("lowest score 10000000007, 8 tiles on best paths\n", export_score(maze, &[String::from("turn=10000000000")]));
+        // starting facing west, going either way round takes three turns...
+        assert_eq!("lowest score 3007, 13 tiles on best paths\n", export_score(maze, &[String::from("start=W")]));
+        // ...unless turning around is cheaper
+        assert_eq!("lowest score 1507, 8 tiles on best paths\n", export_score(maze, &[String::from("start=W"), String::from("reverse=500")]));
+
+        // the nearest of several ends, from the best of several starts
+        let maze = "#######
+#S...E#
+#.###.#
+#S...E#
+#######";
+        assert_eq!("lowest score 4, 10 tiles on best paths\n", export_score(maze, &[]));
+        assert_eq!("no way to reach the end\n", export_score("#S#E#", &[]));
+    }
+
+    #[test]
+    fn best_paths() {
+        assert_eq!("###############
+#.......#....E#
+#.#.###.#.###^#
+#.....#.#...#^#
+#.###.#####.#^#
+#.#.#.......#^#
+#.#.#####.###^#
+#..>>>>>>>>v#^#
+###^#O#####v#^#
+#OO^#O....#v#^#
+#O#^#O###.#v#^#
+#>>^OO#...#v#^#
+#^###.#.#.#v#^#
+#S..#.....#>>^#
+###############
+score 7036, 43 moves, 7 turns, 3 best paths, 45 tiles on best paths
+", export_path(INPUT_SMALL, &[]));
+        assert!(export_path(INPUT_LARGE, &[]).ends_with("score 11048, 59 moves, 11 turns, 2 best paths, 64 tiles on best paths\n"));
+        // free turns can go round in circles forever
+        assert!(export_path(INPUT_SMALL, &[String::from("turn=0")]).ends_with("score 28, 39 moves, 11 turns, infinitely many best paths, 37 tiles on best paths\n"));
+
+        // a row of loops that can each be gone round either way
+        let loops = 70;
+        let wall = "#".repeat(6 * loops + 1);
+        let side = format!("#{}", ".....#".repeat(loops));
+        let middle = format!("S{}E", ".###..".repeat(loops).strip_suffix('.').unwrap());
+        let maze = Maze::parse_and_traverse(&[wall.as_str(), &side, &middle, &side, &wall].join("\n"), Direction::East, &CostModel::PUZZLE);
+        assert_eq!(Some(BigUint::from(2u8).pow(70)), maze.best_path_count());
+        assert_eq!(4 * loops, maze.best_path().unwrap().turns);
+    }
+
+    /// A `size` by `size` maze, for an odd `size`, where every cell opens either north or east
+    /// (making a binary tree), and some cells also open south to make loops.
+    fn generated_maze(size: usize) -> String {
+        let mut lcg = Lcg::new(12345);
+        let mut grid = vec![vec!['#'; size]; size];
+        for row in (1..size - 1).step_by(2) {
+            for col in (1..size - 1).step_by(2) {
+                grid[row][col] = '.';
+                let (can_go_north, can_go_east) = (row > 1, col < size - 2);
+                if can_go_north && (!can_go_east || lcg.below(2) == 0) {
+                    grid[row - 1][col] = '.';
+                } else if can_go_east {
+                    grid[row][col + 1] = '.';
+                }
+                if row < size - 2 && lcg.below(10) == 0 {
+                    grid[row + 1][col] = '.';
+                }
+            }
+        }
+        grid[size - 2][1] = 'S';
+        grid[1][size - 2] = 'E';
+        grid.iter()
+            .map(|row| row.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Takes a while, so only runs when asked: `cargo test --release -- --ignored --nocapture`
+    /// to see how long it takes (a second or two).
+    #[test]
+    #[ignore]
+    fn large_generated_maze() {
+        let input = generated_maze(2001);
+        let started = Instant::now();
+        let maze = Maze::parse_and_traverse(&input, Direction::East, &CostModel::PUZZLE);
+        let score = maze.min_score().unwrap();
+        let best_seats = maze.best_seats_count();
+        eprintln!("2001x2001 maze: lowest score {}, {} tiles on best paths, in {:?}", score, best_seats, started.elapsed());
+        // the end is 1998 tiles up and 1998 across from the start
+        assert!(score >= 2 * 1998 + 1000);
+        assert!(best_seats > 2 * 1998);
+    }
+}
@@ -1,225 +1,402 @@
 use crate::common::Direction;
-use std::cmp::PartialEq;
-use std::collections::{HashMap, HashSet};
+use crate::dot::DotGraph;
+use num::BigUint;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+const DIRECTIONS: [Direction; 4] = [Direction::North, Direction::East, Direction::South, Direction::West];
+
+/// What each kind of move costs. Turning around on the spot costs two turns, unless there's a
+/// `reverse` cost for doing it in one go.
+#[derive(Copy, Clone)]
+pub struct CostModel {
+    pub turn: u64,
+    pub step: u64,
+    pub reverse: Option<u64>,
+}
+
+impl CostModel {
+    pub const PUZZLE: CostModel = CostModel { turn: 1000, step: 1, reverse: None };
+}
 
 pub struct Maze {
-    tiles: Vec<Vec<Tile>>,
+    width: usize,
+    height: usize,
+    /// Every tile, a row at a time.
+    tiles: Vec<Tile>,
+    /// The lowest score to each tile and direction, indexed by `Maze::index`, with `u64::MAX`
+    /// for the ones that can't be reached.
+    scores: Vec<u64>,
+    start_direction: Direction,
+    costs: CostModel,
+}
+
+/// One of the lowest scoring paths, as each tile and direction along it in turn, from a start
+/// tile to an end tile.
+pub struct BestPath {
+    pub score: u64,
+    pub moves: Vec<(Position, Direction)>,
+    /// How many quarter turns the path makes, with turning around counting as two.
+    pub turns: usize,
 }
 
 impl Maze {
-    pub fn parse_and_traverse(input: &str) -> Self {
-        let mut tiles = Maze::parse_maze(input);
-        let mut path_ends: Vec<PathEnd> = Vec::from([
-            PathEnd { position: Maze::start_position(&tiles), direction: Direction::East }
-        ]);
-        while !path_ends.is_empty() {
-            let mut new_path_ends = Vec::new();
-            for path_end in path_ends {
-                Maze::explore_path(&mut tiles, path_end, path_end.turn_clockwise(),
-                                   1000, &mut new_path_ends);
-                Maze::explore_path(&mut tiles, path_end, path_end.turn_anticlockwise(),
-                                   1000, &mut new_path_ends);
-                Maze::explore_path(&mut tiles, path_end, path_end.move_forward(),
-                                   1, &mut new_path_ends);
+    /// Finds the lowest score to every tile and direction, starting from any `S` tile facing
+    /// `start_direction`, with Dijkstra's algorithm: tile and direction pairs are taken off a
+    /// priority queue lowest score first, so each one's score is final the first time it's
+    /// taken off, and any later entries for it are stale and skipped.
+    pub fn parse_and_traverse(input: &str, start_direction: Direction, costs: &CostModel) -> Self {
+        let mut maze = Maze::parse(input, start_direction, costs);
+        let starts = maze.positions(Tile::Start);
+        assert!(!starts.is_empty(), "the maze has no start tile");
+        let mut queue = BinaryHeap::new();
+        for position in starts {
+            let index = maze.index(PathEnd { position, direction: start_direction });
+            maze.scores[index] = 0;
+            queue.push(Reverse((0, index)));
+        }
+        while let Some(Reverse((score, index))) = queue.pop() {
+            if score > maze.scores[index] {
+                continue;
+            }
+            for (next_path_end, score_increase) in maze.moves(maze.path_end(index)) {
+                if maze.tile(next_path_end.position) == Tile::Wall {
+                    continue;
+                }
+                let next_index = maze.index(next_path_end);
+                let next_score = score.saturating_add(score_increase);
+                if next_score < maze.scores[next_index] {
+                    maze.scores[next_index] = next_score;
+                    queue.push(Reverse((next_score, next_index)));
+                }
             }
-            path_ends = new_path_ends;
         }
-        Maze { tiles }
+        maze
     }
 
-    fn parse_maze(input: &str) -> Vec<Vec<Tile>> {
-        input.lines()
-            .map(|line| {
-                line.chars()
-                    .map(Tile::parse)
-                    .collect()
-            })
+    /// Every move that can be made from `path_end`, and what it costs, whether or not it goes
+    /// into a wall.
+    fn moves(&self, path_end: PathEnd) -> impl Iterator<Item = (PathEnd, u64)> + use<> {
+        [
+            Some((path_end.turn_clockwise(), self.costs.turn)),
+            Some((path_end.turn_anticlockwise(), self.costs.turn)),
+            Some((path_end.move_forward(), self.costs.step)),
+            self.costs.reverse.map(|reverse| (path_end.turn_around(), reverse)),
+        ].into_iter().flatten()
+    }
+
+    /// Every move that can lead to `path_end`, and what it costs.
+    fn prev_moves(&self, path_end: PathEnd) -> impl Iterator<Item = (PathEnd, u64)> + use<> {
+        [
+            Some((path_end.turn_clockwise(), self.costs.turn)),
+            Some((path_end.turn_anticlockwise(), self.costs.turn)),
+            Some((path_end.move_backward(), self.costs.step)),
+            self.costs.reverse.map(|reverse| (path_end.turn_around(), reverse)),
+        ].into_iter().flatten()
+    }
+
+    fn positions(&self, wanted: Tile) -> Vec<Position> {
+        self.tiles.iter()
+            .enumerate()
+            .filter(|&(_, &tile)| tile == wanted)
+            .map(|(tile_index, _)| Position { row: tile_index / self.width, col: tile_index % self.width })
             .collect()
     }
 
-    fn explore_path(
-        tiles: &mut [Vec<Tile>],
-        cur_path_end: PathEnd,
-        new_path_end: PathEnd,
-        score_increase: u32,
-        new_path_ends: &mut Vec<PathEnd>,
-    ) {
-        let (new_row, new_col, new_dir) = new_path_end.destructure();
-        let new_tile = &tiles[new_row][new_col];
-        if let Tile::Path { scores: new_scores, .. } = new_tile {
-            let (cur_row, cur_col, cur_dir) = cur_path_end.destructure();
-            let Tile::Path { scores: cur_scores, .. } = &tiles[cur_row][cur_col] else {
-                panic!("somehow we are currently in a wall.");
-            };
-            let new_score_via_cur = cur_scores.get_score(cur_dir) + score_increase;
-            if new_score_via_cur < new_scores.get_score(new_dir) {
-                let new_tile_mut = &mut tiles[new_row][new_col];
-                let Tile::Path { scores: new_scores, .. } = new_tile_mut else {
-                    panic!("this can't happen, it's the same path tile we already got!");
-                };
-                new_scores.set_score(new_score_via_cur, new_dir);
-                new_path_ends.push(new_path_end);
-            }
+    /// The maze with nothing reached yet. Short lines are padded out with wall.
+    fn parse(input: &str, start_direction: Direction, costs: &CostModel) -> Self {
+        let lines: Vec<&str> = input.lines().collect();
+        let width = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+        let height = lines.len();
+        let mut tiles = Vec::with_capacity(width * height);
+        for line in lines {
+            let start = tiles.len();
+            tiles.extend(line.chars().map(Tile::parse));
+            tiles.resize(start + width, Tile::Wall);
         }
+        let scores = vec![u64::MAX; tiles.len() * DIRECTIONS.len()];
+        Maze { width, height, tiles, scores, start_direction, costs: *costs }
     }
 
-    pub fn min_score(&self) -> u32 {
-        let end = &self.tiles[1][&self.tiles[0].len() - 2];
-        match end {
-            Tile::Path { path_type: PathType::End, scores } => scores.min(),
-            _ => panic!("expected end tile is not actually the end tile"),
+    /// Where `path_end`'s score is kept, which has to be inside the maze.
+    fn index(&self, path_end: PathEnd) -> usize {
+        let direction = match path_end.direction {
+            Direction::North => 0,
+            Direction::East => 1,
+            Direction::South => 2,
+            Direction::West => 3,
+        };
+        (path_end.position.row * self.width + path_end.position.col) * DIRECTIONS.len() + direction
+    }
+
+    fn path_end(&self, index: usize) -> PathEnd {
+        let tile_index = index / DIRECTIONS.len();
+        PathEnd {
+            position: Position { row: tile_index / self.width, col: tile_index % self.width },
+            direction: DIRECTIONS[index % DIRECTIONS.len()],
         }
     }
 
+    /// The lowest score to reach any end tile, or `None` if none can be reached.
+    pub fn min_score(&self) -> Option<u64> {
+        self.end_positions().into_iter()
+            .flat_map(|position| DIRECTIONS.map(|direction| self.score(PathEnd { position, direction })))
+            .min()
+            .filter(|&score| score != u64::MAX)
+    }
+
     pub fn best_seats_count(&self) -> usize {
-        let mut best_path_ends = Vec::new();
-        let end_position = self.end_position();
-        best_path_ends.push(PathEnd {
-            position: end_position,
-            direction: match &self.tiles[end_position.row][end_position.col] {
-                Tile::Path { path_type: PathType::End, scores } => scores.min_dir(),
-                _ => panic!("expected end tile is not actually the end tile"),
+        self.best_path_ends().iter()
+            .map(|path_end| path_end.position)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// The first lowest scoring path found, going back from the end with the lowest score.
+    pub fn best_path(&self) -> Option<BestPath> {
+        let score = self.min_score()?;
+        let end = self.best_ends(score)[0];
+        // search back along moves that keep to the lowest scores until reaching a start,
+        // remembering which way each path end was reached from so the path can be followed
+        // forwards again
+        let mut next_path_ends = HashMap::new();
+        let mut pending = vec![end];
+        let mut start = None;
+        while let Some(path_end) = pending.pop() {
+            if self.is_start(path_end) {
+                start = Some(path_end);
+                break;
             }
-        });
-        let mut best_seats = HashSet::new();
-        while !best_path_ends.is_empty() {
-            let mut new_best_path_starts = Vec::new();
-            for best_path_end in best_path_ends {
-                let (best_row, best_col, best_dir) = best_path_end.destructure();
-                let Tile::Path {
-                    scores: best_scores,
-                    ..
-                } = &self.tiles[best_row][best_col] else {
-                    panic!("somehow we are currently in a wall.");
-                };
-                best_seats.insert(best_path_end.position);
-                let prev_path_ends = [
-                    best_path_end.turn_clockwise(),
-                    best_path_end.turn_anticlockwise(),
-                    best_path_end.move_backward(),
-                ];
-                for prev_path_end in prev_path_ends {
-                    let (prev_row, prev_col, prev_dir) = prev_path_end.destructure();
-                    let Tile::Path {
-                        scores: prev_scores,
-                        ..
-                    } = &self.tiles[prev_row][prev_col] else {
-                        continue;
-                    };
-                    if prev_scores.get_score(prev_dir) < best_scores.get_score(best_dir) {
-                        new_best_path_starts.push(prev_path_end);
-                    }
+            for (prev_path_end, score_increase) in self.prev_moves(path_end) {
+                let on_best_path = self.score(prev_path_end).checked_add(score_increase) == Some(self.score(path_end));
+                if on_best_path && prev_path_end != end && !next_path_ends.contains_key(&prev_path_end) {
+                    next_path_ends.insert(prev_path_end, path_end);
+                    pending.push(prev_path_end);
                 }
             }
-            best_path_ends = new_best_path_starts;
         }
-        best_seats.len()
+        let mut path_end = start.expect("there should be a path back to a start");
+        let mut moves = vec![(path_end.position, path_end.direction)];
+        while path_end != end {
+            path_end = next_path_ends[&path_end];
+            moves.push((path_end.position, path_end.direction));
+        }
+        let turns = moves.windows(2)
+            .map(|pair| match pair {
+                [(_, from), (_, to)] if to == from => 0,
+                [(_, from), (_, to)] if *to == from.reverse() => 2,
+                _ => 1,
+            })
+            .sum();
+        Some(BestPath { score, moves, turns })
     }
 
-    fn start_position(tiles: &[Vec<Tile>]) -> Position {
-        match &tiles[tiles.len() - 2][1] {
-            Tile::Path {
-                path_type: PathType::Start, ..
-            } => Position { row: tiles.len() - 2, col: 1},
-            _ => panic!("expected start tile is not actually the start tile"),
+    /// How many different lowest scoring paths there are from any start to any end, or `None`
+    /// if there are infinitely many because some moves are free and can be repeated forever.
+    pub fn best_path_count(&self) -> Option<BigUint> {
+        let Some(score) = self.min_score() else {
+            return Some(BigUint::ZERO);
+        };
+        let best_path_ends = self.best_path_ends();
+        let best_moves = |path_end: PathEnd| -> Vec<PathEnd> {
+            self.moves(path_end)
+                .filter(|&(next_path_end, score_increase)| {
+                    best_path_ends.contains(&next_path_end) &&
+                        self.score(path_end).checked_add(score_increase) == Some(self.score(next_path_end))
+                })
+                .map(|(next_path_end, _)| next_path_end)
+                .collect()
+        };
+        // count the paths to each path end in topological order, so that every path end's
+        // count is complete before it's added on to the path ends after it
+        let mut moves_in: HashMap<PathEnd, usize> = best_path_ends.iter().map(|&path_end| (path_end, 0)).collect();
+        for &path_end in &best_path_ends {
+            for next_path_end in best_moves(path_end) {
+                *moves_in.get_mut(&next_path_end).unwrap() += 1;
+            }
+        }
+        let mut counts: HashMap<PathEnd, BigUint> = best_path_ends.iter()
+            .map(|&path_end| (path_end, BigUint::from(u8::from(self.is_start(path_end)))))
+            .collect();
+        let mut ready: Vec<PathEnd> = moves_in.iter()
+            .filter(|&(_, &count)| count == 0)
+            .map(|(&path_end, _)| path_end)
+            .collect();
+        let mut done = 0;
+        while let Some(path_end) = ready.pop() {
+            done += 1;
+            let count = counts[&path_end].clone();
+            for next_path_end in best_moves(path_end) {
+                *counts.get_mut(&next_path_end).unwrap() += &count;
+                let remaining = moves_in.get_mut(&next_path_end).unwrap();
+                *remaining -= 1;
+                if *remaining == 0 {
+                    ready.push(next_path_end);
+                }
+            }
+        }
+        if done < best_path_ends.len() {
+            return None;
         }
+        Some(self.best_ends(score).iter().map(|path_end| &counts[path_end]).sum())
     }
 
-    fn end_position(&self) -> Position {
-        match &self.tiles[1][&self.tiles[0].len() - 2] {
-            Tile::Path {
-                path_type: PathType::End, ..
-            } => Position { row: 1, col: &self.tiles[0].len() - 2 },
-            _ => panic!("expected end tile is not actually the end tile"),
+    /// The maze with arrows along the best path showing which way it leaves each tile, and `O`
+    /// on the other tiles that are on any of the lowest scoring paths.
+    pub fn render_best_path(&self) -> String {
+        let best_positions: HashSet<Position> = self.best_path_ends().iter()
+            .map(|path_end| path_end.position)
+            .collect();
+        let arrows: HashMap<Position, Direction> = self.best_path()
+            .map(|best_path| best_path.moves.into_iter().collect())
+            .unwrap_or_default();
+        let mut s = String::new();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let position = Position { row, col };
+                s.push(match self.tile(position) {
+                    Tile::Wall => '#',
+                    Tile::Start => 'S',
+                    Tile::End => 'E',
+                    Tile::Path => match arrows.get(&position) {
+                        Some(&direction) => direction_arrow(direction),
+                        None if best_positions.contains(&position) => 'O',
+                        None => '.',
+                    },
+                });
+            }
+            s.push('\n');
         }
+        s
     }
-}
-
-enum Tile {
-    Wall,
-    Path {
-        path_type: PathType,
-        scores: PathScores,
-    },
-}
 
-impl Tile {
-    fn parse(c: char) -> Self {
-        match c {
-            '#' => Tile::Wall,
-            '.' => Tile::new_path(PathType::Path),
-            'S' => Tile::new_path(PathType::Start),
-            'E' => Tile::new_path(PathType::End),
-            _ => panic!("unexpected tile char: {}", c),
+    pub fn to_dot(&self) -> String {
+        let best_path_ends = self.best_path_ends();
+        let mut graph = DotGraph::new("maze");
+        let open_path_ends = || (0..self.scores.len())
+            .map(|index| self.path_end(index))
+            .filter(|path_end| self.tile(path_end.position) != Tile::Wall);
+        for path_end in open_path_ends() {
+            let Position { row, col } = path_end.position;
+            let score = self.score(path_end);
+            let label = format!("{},{} {}", row, col, direction_char(path_end.direction));
+            let tooltip = if score == u64::MAX {
+                String::from("unreachable")
+            } else {
+                format!("score {}", score)
+            };
+            let mut attributes = vec![("label", label.as_str()), ("tooltip", tooltip.as_str())];
+            if best_path_ends.contains(&path_end) {
+                attributes.push(("color", "red"));
+            }
+            if self.tile(path_end.position) != Tile::Path && path_end.direction == Direction::East {
+                attributes.push(("shape", "doublecircle"));
+            }
+            graph.add_node(&path_end.dot_id(), &attributes);
         }
+        for path_end in open_path_ends() {
+            for (next_path_end, score_increase) in self.moves(path_end) {
+                self.add_dot_edge(&mut graph, &best_path_ends, path_end, next_path_end, score_increase);
+            }
+        }
+        graph.to_string()
     }
 
-    fn new_path(path_type: PathType) -> Self {
-        let east_score = if path_type == PathType::Start {
-            0
+    fn add_dot_edge(
+        &self,
+        graph: &mut DotGraph,
+        best_path_ends: &HashSet<PathEnd>,
+        from: PathEnd,
+        to: PathEnd,
+        score_increase: u64,
+    ) {
+        if self.tile(to.position) == Tile::Wall {
+            return;
+        }
+        let score_increase_str = score_increase.to_string();
+        let on_best_path = best_path_ends.contains(&from) && best_path_ends.contains(&to) &&
+            self.score(from).checked_add(score_increase) == Some(self.score(to));
+        if on_best_path {
+            graph.add_edge(&from.dot_id(), &to.dot_id(), &[
+                ("label", &score_increase_str),
+                ("color", "red"),
+                ("penwidth", "2"),
+            ]);
         } else {
-            u32::MAX
-        };
-        Self::Path {
-            path_type,
-            scores: PathScores {
-                north_score: u32::MAX,
-                east_score,
-                south_score: u32::MAX,
-                west_score: u32::MAX,
-            },
+            graph.add_edge(&from.dot_id(), &to.dot_id(), &[("label", &score_increase_str)]);
         }
     }
-}
 
-#[derive(Eq, PartialEq)]
-enum PathType { Start, End, Path }
+    /// Every tile and direction on any of the lowest scoring paths to any of the end tiles.
+    fn best_path_ends(&self) -> HashSet<PathEnd> {
+        let Some(min_score) = self.min_score() else {
+            return HashSet::new();
+        };
+        let mut best_path_ends = self.best_ends(min_score);
+        let mut best_path_ends_found: HashSet<PathEnd> = best_path_ends.iter().copied().collect();
+        while let Some(best_path_end) = best_path_ends.pop() {
+            let best_score = self.score(best_path_end);
+            for (prev_path_end, score_increase) in self.prev_moves(best_path_end) {
+                if self.tile(prev_path_end.position) == Tile::Wall {
+                    continue;
+                }
+                if self.score(prev_path_end).checked_add(score_increase) == Some(best_score) &&
+                    best_path_ends_found.insert(prev_path_end) {
+                    best_path_ends.push(prev_path_end);
+                }
+            }
+        }
+        best_path_ends_found
+    }
 
-struct PathScores {
-    north_score: u32,
-    east_score: u32,
-    south_score: u32,
-    west_score: u32,
-}
+    /// The tile at `position`, where everything outside the maze counts as wall.
+    fn tile(&self, position: Position) -> Tile {
+        if position.row < self.height && position.col < self.width {
+            self.tiles[position.row * self.width + position.col]
+        } else {
+            Tile::Wall
+        }
+    }
 
-impl PathScores {
-    fn get_score(&self, direction: Direction) -> u32 {
-        match direction {
-            Direction::North => self.north_score,
-            Direction::East => self.east_score,
-            Direction::South => self.south_score,
-            Direction::West => self.west_score,
+    fn score(&self, path_end: PathEnd) -> u64 {
+        if self.tile(path_end.position) == Tile::Wall {
+            u64::MAX
+        } else {
+            self.scores[self.index(path_end)]
         }
     }
 
-    fn set_score(&mut self, new_score: u32, direction: Direction) {
-        let score: &mut u32 = match direction {
-            Direction::North => &mut self.north_score,
-            Direction::East => &mut self.east_score,
-            Direction::South => &mut self.south_score,
-            Direction::West => &mut self.west_score,
-        };
-        *score = new_score;
+    /// The directions on the end tiles that can be reached with the lowest score.
+    fn best_ends(&self, min_score: u64) -> Vec<PathEnd> {
+        self.end_positions().into_iter()
+            .flat_map(|position| DIRECTIONS.map(|direction| PathEnd { position, direction }))
+            .filter(|&path_end| self.score(path_end) == min_score)
+            .collect()
     }
 
-    fn min(&self) -> u32 {
-        *[self.north_score, self.east_score, self.south_score, self.west_score]
-            .iter()
-            .min()
-            .unwrap()
+    fn is_start(&self, path_end: PathEnd) -> bool {
+        path_end.direction == self.start_direction && self.tile(path_end.position) == Tile::Start
+    }
+
+    fn end_positions(&self) -> Vec<Position> {
+        let end_positions = self.positions(Tile::End);
+        assert!(!end_positions.is_empty(), "the maze has no end tile");
+        end_positions
     }
+}
+
+#[derive(Eq, PartialEq, Copy, Clone)]
+enum Tile { Wall, Start, End, Path }
 
-    fn min_dir(&self) -> Direction {
-        let scores_by_dir: HashMap<Direction, u32> = [
-            (Direction::North, self.north_score),
-            (Direction::East, self.east_score),
-            (Direction::South, self.south_score),
-            (Direction::West, self.west_score),
-        ].into_iter().collect();
-        *scores_by_dir.iter()
-            .min_by_key(|(_, score)| *score)
-            .map(|(dir, _)| dir)
-            .unwrap()
+impl Tile {
+    fn parse(c: char) -> Self {
+        match c {
+            '#' => Tile::Wall,
+            '.' => Tile::Path,
+            'S' => Tile::Start,
+            'E' => Tile::End,
+            _ => panic!("unexpected tile char: {}", c),
+        }
     }
 }
 
@@ -248,6 +425,13 @@ impl PathEnd {
         }
     }
 
+    fn turn_around(&self) -> PathEnd {
+        Self {
+            position: self.position,
+            direction: self.direction.reverse(),
+        }
+    }
+
     fn move_backward(&self) -> PathEnd {
         Self {
             position: self.position.move_forward(self.direction.reverse()),
@@ -255,8 +439,26 @@ impl PathEnd {
         }
     }
 
-    fn destructure(&self) -> (usize, usize, Direction) {
-        (self.position.row, self.position.col, self.direction)
+    fn dot_id(&self) -> String {
+        format!("{},{},{}", self.position.row, self.position.col, direction_char(self.direction))
+    }
+}
+
+fn direction_arrow(direction: Direction) -> char {
+    match direction {
+        Direction::North => '^',
+        Direction::East => '>',
+        Direction::South => 'v',
+        Direction::West => '<',
+    }
+}
+
+fn direction_char(direction: Direction) -> char {
+    match direction {
+        Direction::North => 'N',
+        Direction::East => 'E',
+        Direction::South => 'S',
+        Direction::West => 'W',
     }
 }
 
@@ -264,12 +466,14 @@ impl PathEnd {
 pub struct Position { row: usize, col: usize }
 
 impl Position {
+    /// The next position in `direction`, which off the top or left edge wraps around to a
+    /// position far outside the maze.
     fn move_forward(&self, direction: Direction) -> Self {
         match direction {
-            Direction::North => Self { row: self.row - 1, col: self.col },
+            Direction::North => Self { row: self.row.wrapping_sub(1), col: self.col },
             Direction::East => Self { row: self.row, col: self.col + 1 },
             Direction::South => Self { row: self.row + 1, col: self.col },
-            Direction::West => Self { row: self.row, col: self.col - 1 },
+            Direction::West => Self { row: self.row, col: self.col.wrapping_sub(1) },
         }
     }
 }
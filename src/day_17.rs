@@ -1,7 +1,14 @@
+mod assembler;
 mod computer;
+mod debugger;
+mod disassembler;
 
-use crate::common::DaySpec;
-use crate::day_17::computer::Computer;
+use crate::common::{DaySpec, Export};
+use crate::day_17::assembler::assemble;
+use crate::day_17::computer::{Computer, Register, Registers};
+use crate::day_17::debugger::{Breakpoint, Debugger, Stop};
+use crate::day_17::disassembler::{instruction_text, listing, pseudo_code, source};
+use std::fs;
 
 pub const DAY_SEVENTEEN: DaySpec<String, u64> = DaySpec {
     day_num: 17,
@@ -9,6 +16,38 @@ pub const DAY_SEVENTEEN: DaySpec<String, u64> = DaySpec {
     part_1,
     part_2_name: "lowest A value that outputs the input program",
     part_2,
+    exports: &[
+        Export {
+            name: "assemble",
+            description: "assembles the mnemonic source in source=FILE (as written by the source \
+                export) into a program line for an input",
+            run: export_assemble,
+            needs_input: false,
+        },
+        Export {
+            name: "disassemble",
+            description: "the program as mnemonics after their addresses, with combo operands shown \
+                as registers and invalid ones flagged, then as pseudo-code",
+            run: export_disassemble,
+            needs_input: true,
+        },
+        Export {
+            name: "source",
+            description: "the program as source for the assemble export, with labels on the \
+                instructions jumped to",
+            run: export_source,
+            needs_input: true,
+        },
+        Export {
+            name: "trace",
+            description: "every instruction run, with the registers before and after and any \
+                output, then why it stopped; optional args are a=N, b=N and c=N to set the \
+                registers, break=ADDRESS, output=any|N for output breakpoints, watch=A|B|C, and \
+                limit=N instructions (default 10000)",
+            run: export_trace,
+            needs_input: true,
+        },
+    ],
 };
 
 fn part_1(input: &str) -> String {
@@ -52,6 +91,106 @@ fn part_2(input: &str) -> u64 {
     panic!("no valid input found");
 }
 
+fn export_disassemble(input: &str, _args: &[String]) -> String {
+    let (_, _, _, program) = parse_input(input);
+    format!("{}\n{}", listing(&program), pseudo_code(&program))
+}
+
+fn export_source(input: &str, _args: &[String]) -> String {
+    let (_, _, _, program) = parse_input(input);
+    source(&program)
+}
+
+fn export_assemble(_input: &str, args: &[String]) -> String {
+    let mut path = None;
+    for arg in args {
+        match arg.split_once('=') {
+            Some(("source", value)) => path = Some(value),
+            _ => panic!("unknown option: {}", arg),
+        }
+    }
+    let path = path.expect("the source=FILE arg is needed");
+    let text = fs::read_to_string(path).unwrap_or_else(|e| panic!("can't read {}: {}", path, e));
+    match assemble(&text) {
+        Ok(program) => format!("Program: {}\n", program_str(&program)),
+        Err(error) => format!("{}\n", error),
+    }
+}
+
+fn export_trace(input: &str, args: &[String]) -> String {
+    let (mut reg_a, mut reg_b, mut reg_c, program) = parse_input(input);
+    let mut breakpoints = Vec::new();
+    let mut watches = Vec::new();
+    let mut limit = 10000;
+    for arg in args {
+        let (key, value) = arg.split_once('=').unwrap_or_else(|| panic!("unknown option: {}", arg));
+        match key {
+            "a" => reg_a = value.parse().unwrap(),
+            "b" => reg_b = value.parse().unwrap(),
+            "c" => reg_c = value.parse().unwrap(),
+            "break" => breakpoints.push(Breakpoint::Address(value.parse().unwrap())),
+            "output" if value == "any" => breakpoints.push(Breakpoint::Output),
+            "output" => breakpoints.push(Breakpoint::OutputValue(value.parse().unwrap())),
+            "watch" => watches.push(match value {
+                "A" => Register::A,
+                "B" => Register::B,
+                "C" => Register::C,
+                _ => panic!("unknown register: {}", value),
+            }),
+            "limit" => limit = value.parse().unwrap(),
+            _ => panic!("unknown option: {}", key),
+        }
+    }
+    let mut debugger = Debugger::new(Computer::new(reg_a, reg_b, reg_c, &program));
+    for breakpoint in breakpoints {
+        debugger.add_breakpoint(breakpoint);
+    }
+    for register in watches {
+        debugger.watch(register);
+    }
+    debugger.set_limit(limit);
+    let stop = debugger.run();
+
+    let width = debugger.trace().len().to_string().len();
+    let mut s = String::new();
+    for (step, entry) in debugger.trace().iter().enumerate() {
+        s.push_str(&format!(
+            "{:>width$}  {:>2}  {:<5}  {}  ->  {}",
+            step,
+            entry.address,
+            instruction_text(&entry.instruction),
+            registers_str(&entry.before),
+            registers_str(&entry.after),
+        ));
+        if let Some(value) = entry.output {
+            s.push_str(&format!("  out {}", value));
+        }
+        s.push('\n');
+    }
+    s.push_str(&match stop {
+        Stop::Halted => String::from("halted"),
+        Stop::Breakpoint(Breakpoint::Address(address)) => format!("breakpoint at address {}", address),
+        Stop::Breakpoint(Breakpoint::Output) => String::from("breakpoint on output"),
+        Stop::Breakpoint(Breakpoint::OutputValue(value)) => format!("breakpoint on output {}", value),
+        Stop::Watch { register, before, after } => format!("register {:?} changed from {} to {}", register, before, after),
+        Stop::Limit => format!("stopped after {} instructions without halting", limit),
+        Stop::Loop { since } => format!("back in the same state as after {} instructions, so it never halts", since),
+    });
+    s.push_str(&format!("\noutput: {}\n", debugger.computer().get_output_str()));
+    s
+}
+
+fn registers_str(registers: &Registers) -> String {
+    format!("A={} B={} C={}", registers.a, registers.b, registers.c)
+}
+
+fn program_str(program: &[u8]) -> String {
+    program.iter()
+        .map(u8::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 fn init_reg_a_values(program: &[u8]) -> Vec<u64> {
     let mut reg_a_values = Vec::new();
     // initialise with all valid 10-bit numbers
@@ -94,6 +233,7 @@ fn parse_program(line: &str) -> Vec<u8> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::day_17::assembler::AssembleError;
 
     #[test]
     fn part_1_sample() {
@@ -114,4 +254,175 @@ Register C: 0
 Program: 0,3,5,4,3,0";
         assert_eq!(117440, part_2(input));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn disassemble() {
+        let input = "Register A: 2024
+Register B: 0
+Register C: 0
+
+Program: 2,4,1,1,7,5,4,3,1,4,0,3,5,5,3,0";
+        assert_eq!(" 0  bst A
+ 2  bxl 1
+ 4  cdv B
+ 6  bxc 3
+ 8  bxl 4
+10  adv 3
+12  out B
+14  jnz 0
+
+do {
+    B = A % 8
+    B = B ^ 1
+    C = A >> B
+    B = B ^ C
+    B = B ^ 4
+    A = A >> 3
+    output B % 8
+} while A != 0
+", export_disassemble(input, &[]));
+
+        // combo operand 7 doesn't mean anything, and a jump that isn't back at the end isn't a loop
+        let program = [1, 7, 5, 7, 3, 6, 2, 2, 4, 0];
+        assert_eq!("0  bxl 7
+2  out 7  ; invalid combo operand 7
+4  jnz 6
+6  bst 2
+8  bxc
+", listing(&program));
+        assert_eq!("B = B ^ 7
+output <invalid 7>
+if A != 0 goto 6
+B = 2
+B = B ^ C
+", pseudo_code(&program));
+        assert_eq!("    bxl 7
+    out 7!  ; invalid combo operand 7
+    jnz at_6
+at_6:
+    bst 2
+    bxc
+", source(&program));
+
+        // an opcode left over at the end has no operand, so the computer halts there
+        let program = [0, 3, 5, 4, 3, 0, 5];
+        assert_eq!("0  adv 3
+2  out A
+4  jnz 0
+6  out  ; no operand, so the program halts here
+", listing(&program));
+        assert!(source(&program).ends_with("    jnz loop\n    out -  ; no operand, so the program halts here\n"));
+
+        // the computer jumps to instruction 3 / 2, so an odd target goes to the opcode before it
+        assert_eq!("B = A % 8
+do {
+    A = A >> 3
+    output B % 8
+} while A != 0
+", pseudo_code(&[2, 4, 0, 3, 5, 5, 3, 3]));
+        assert_eq!("if A != 0 goto 2\nB = B ^ 1\n", pseudo_code(&[3, 3, 1, 1]));
+    }
+
+    #[test]
+    fn assemble_source() {
+        // disassembling to source and assembling it again gives back the same program
+        for program in [
+            vec![0, 1, 5, 4, 3, 0],
+            vec![0, 3, 5, 4, 3, 0],
+            vec![2, 4, 1, 1, 7, 5, 4, 3, 1, 4, 0, 3, 5, 5, 3, 0],
+            vec![1, 7, 3, 6, 3, 3, 2, 2, 4, 0],
+            vec![1, 7, 5, 7, 3, 6, 2, 2, 4, 0],
+            vec![0, 3, 5, 4, 3, 0, 5],
+            vec![7],
+        ] {
+            assert_eq!(Ok(program.clone()), assemble(&source(&program)));
+        }
+        assert_eq!("loop:
+    adv 3
+    out A
+    jnz loop
+", source(&[0, 3, 5, 4, 3, 0]));
+
+        let text = "; counts down A in threes
+start:  bst 0      ; B = 0
+loop:
+    ADV 3
+    out a
+    bxc
+    jnz loop
+    jnz start
+";
+        assert_eq!(Ok(vec![2, 0, 0, 3, 5, 4, 4, 0, 3, 2, 3, 0]), assemble(text));
+
+        let error = |line: usize, message: &str| Err(AssembleError { line, message: String::from(message) });
+        assert_eq!(error(2, "unknown instruction: mul"), assemble("adv 1\nmul 2"));
+        assert_eq!(error(1, "out needs an operand"), assemble("out ; nothing"));
+        assert_eq!(error(1, "combo operand 7 is invalid, so it has to be written 7!"), assemble("bst 7"));
+        assert_eq!(Ok(vec![2, 7]), assemble("bst 7!"));
+        assert_eq!(error(1, "invalid operand for bxl: 7!"), assemble("bxl 7!"));
+        assert_eq!(error(1, "only the last instruction can have no operand"), assemble("out -\nadv 1"));
+        assert_eq!(error(1, "operand 8 doesn't fit in 3 bits"), assemble("bxl 8"));
+        assert_eq!(error(1, "bxl takes a literal operand, not a register"), assemble("bxl A"));
+        assert_eq!(error(1, "unexpected B after the operand"), assemble("adv A B"));
+        assert_eq!(error(3, "label top is already defined on line 1"), assemble("top:\nadv 1\ntop: adv 2"));
+        assert_eq!(error(1, "no label named end"), assemble("jnz end"));
+        assert_eq!(error(1, "invalid label: 2nd"), assemble("2nd: adv 1"));
+        let far = format!("{}far: jnz far", "adv 1\n".repeat(4));
+        assert_eq!(error(5, "label far is at address 8, which doesn't fit in 3 bits"), assemble(&far));
+    }
+
+    #[test]
+    fn trace() {
+        let input = "Register A: 729
+Register B: 0
+Register C: 0
+
+Program: 0,1,5,4,3,0";
+        let args = |args: &[&str]| args.iter().map(|&arg| String::from(arg)).collect::<Vec<_>>();
+        let trace = export_trace(input, &[]);
+        assert!(trace.starts_with(" 0   0  adv 1  A=729 B=0 C=0  ->  A=364 B=0 C=0
+ 1   2  out A  A=364 B=0 C=0  ->  A=364 B=0 C=0  out 4
+ 2   4  jnz 0  A=364 B=0 C=0  ->  A=364 B=0 C=0
+"));
+        assert!(trace.ends_with("29   4  jnz 0  A=0 B=0 C=0  ->  A=0 B=0 C=0
+halted
+output: 4,6,3,5,6,3,5,2,1,0
+"));
+        assert_eq!("0   0  adv 1  A=729 B=0 C=0  ->  A=364 B=0 C=0
+1   2  out A  A=364 B=0 C=0  ->  A=364 B=0 C=0  out 4
+breakpoint at address 4
+output: 4
+", export_trace(input, &args(&["break=4"])));
+        assert!(export_trace(input, &args(&["output=6"])).ends_with("out 6\nbreakpoint on output 6\noutput: 4,6\n"));
+        assert!(export_trace(input, &args(&["watch=A"])).ends_with("register A changed from 729 to 364\noutput: \n"));
+        assert!(export_trace(input, &args(&["limit=5"])).ends_with("stopped after 5 instructions without halting\noutput: 4,6\n"));
+        assert!(export_trace(input, &args(&["a=1", "watch=B"])).ends_with("halted\noutput: 0\n"));
+        // dividing A by 2 to the power of 64 or more (here, A itself) just leaves zero
+        let shift_by_a = input.replace("0,1,5,4,3,0", "0,4");
+        assert!(export_trace(&shift_by_a, &args(&["a=64"])).starts_with("0   0  adv A  A=64 B=0 C=0  ->  A=0 B=0 C=0\n"));
+
+        // carrying on from a breakpoint runs on to the next time it's reached
+        let mut debugger = Debugger::new(Computer::new(729, 0, 0, &[0, 1, 5, 4, 3, 0]));
+        debugger.add_breakpoint(Breakpoint::Address(0));
+        debugger.watch(Register::B);
+        assert_eq!(Stop::Breakpoint(Breakpoint::Address(0)), debugger.run());
+        assert_eq!(3, debugger.trace().len());
+        assert_eq!(Stop::Breakpoint(Breakpoint::Address(0)), debugger.run());
+        assert_eq!(6, debugger.trace().len());
+        assert_eq!(Some(91), debugger.step().map(|entry| entry.after.a));
+        assert_eq!("4,6", debugger.computer().get_output_str());
+
+        // flipping B back and forth never halts, which shows up as soon as the state repeats
+        let mut debugger = Debugger::new(Computer::new(1, 0, 0, &[1, 3, 3, 0]));
+        assert_eq!(Stop::Loop { since: 0 }, debugger.run());
+        assert_eq!(4, debugger.trace().len());
+
+        // each run looks for a loop afresh, so a breakpoint inside one keeps stopping there
+        let mut debugger = Debugger::new(Computer::new(1, 0, 0, &[1, 3, 3, 0]));
+        debugger.add_breakpoint(Breakpoint::Address(2));
+        for _ in 0..4 {
+            assert_eq!(Stop::Breakpoint(Breakpoint::Address(2)), debugger.run());
+        }
+        assert_eq!(7, debugger.trace().len());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::day_17::computer::Instruction;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Display;
+
+/// Something wrong with the source, and which line (counting from 1) it's on.
+#[derive(Debug, PartialEq)]
+pub struct AssembleError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+enum Operand {
+    Value(u8),
+    Label(String),
+    /// No operand at all, which only the last opcode can have.
+    Missing,
+}
+
+/// Turns mnemonic source back into a program, two numbers per instruction.
+///
+/// Each line holds an instruction such as `bst A` or `jnz 0`, optionally after any number of
+/// labels such as `loop:`, and anything after a `;` is a comment. Combo operands can be given as
+/// the registers `A`, `B` and `C` or as numbers up to 6, literal operands as numbers up to 7, and
+/// `jnz` can jump to a label, as long as it's at an address that fits in 3 bits. `bxc` ignores
+/// its operand, so it can be left off. Combo operand 7 doesn't stand for anything, so it has to
+/// be written `7!` to show it's meant, and a program can end with an opcode that has no operand
+/// by writing `-` for it.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AssembleError> {
+    let mut labels: HashMap<&str, (usize, usize)> = HashMap::new();
+    let mut instructions = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let line_num = index + 1;
+        let error = |message: String| AssembleError { line: line_num, message };
+        let mut text = line.split_once(';').map_or(line, |(code, _)| code).trim();
+        while let Some((label, rest)) = text.split_once(':') {
+            let label = label.trim();
+            if !is_label(label) {
+                return Err(error(format!("invalid label: {}", label)));
+            }
+            if let Some((_, defined_on)) = labels.insert(label, (instructions.len() * 2, line_num)) {
+                return Err(error(format!("label {} is already defined on line {}", label, defined_on)));
+            }
+            text = rest.trim();
+        }
+        let mut tokens = text.split_whitespace();
+        let Some(mnemonic) = tokens.next() else {
+            continue;
+        };
+        let opcode = (0..8)
+            .find(|&opcode| Instruction::new(opcode, 0).mnemonic() == mnemonic.to_ascii_lowercase())
+            .ok_or_else(|| error(format!("unknown instruction: {}", mnemonic)))?;
+        let instruction = Instruction::new(opcode, 0);
+        let operand = match tokens.next() {
+            Some(operand) => parse_operand(&instruction, operand).map_err(error)?,
+            None if matches!(instruction, Instruction::Bxc(_)) => Operand::Value(0),
+            None => return Err(error(format!("{} needs an operand", mnemonic))),
+        };
+        if let Some(extra) = tokens.next() {
+            return Err(error(format!("unexpected {} after the operand", extra)));
+        }
+        instructions.push((line_num, opcode, operand));
+    }
+
+    let mut program = Vec::new();
+    let instruction_count = instructions.len();
+    for (index, (line_num, opcode, operand)) in instructions.into_iter().enumerate() {
+        let operand = match operand {
+            Operand::Value(value) => value,
+            Operand::Missing if index + 1 == instruction_count => {
+                program.push(opcode);
+                continue;
+            }
+            Operand::Missing => {
+                return Err(AssembleError {
+                    line: line_num,
+                    message: String::from("only the last instruction can have no operand"),
+                });
+            }
+            Operand::Label(label) => {
+                let &(address, _) = labels.get(label.as_str())
+                    .ok_or_else(|| AssembleError { line: line_num, message: format!("no label named {}", label) })?;
+                u8::try_from(address).ok().filter(|&address| address < 8)
+                    .ok_or_else(|| AssembleError {
+                        line: line_num,
+                        message: format!("label {} is at address {}, which doesn't fit in 3 bits", label, address),
+                    })?
+            }
+        };
+        program.extend([opcode, operand]);
+    }
+    Ok(program)
+}
+
+fn parse_operand(instruction: &Instruction, operand: &str) -> Result<Operand, String> {
+    let register = match operand {
+        "A" | "a" => Some(4),
+        "B" | "b" => Some(5),
+        "C" | "c" => Some(6),
+        _ => None,
+    };
+    if let Some(register) = register {
+        return if instruction.takes_combo() {
+            Ok(Operand::Value(register))
+        } else {
+            Err(format!("{} takes a literal operand, not a register", instruction.mnemonic()))
+        };
+    }
+    match operand {
+        "7!" if instruction.takes_combo() => return Ok(Operand::Value(7)),
+        "-" => return Ok(Operand::Missing),
+        _ => {}
+    }
+    if let Ok(value) = operand.parse::<u8>() {
+        return match value {
+            7 if instruction.takes_combo() => Err(String::from("combo operand 7 is invalid, so it has to be written 7!")),
+            0..=7 => Ok(Operand::Value(value)),
+            _ => Err(format!("operand {} doesn't fit in 3 bits", value)),
+        };
+    }
+    if matches!(instruction, Instruction::Jnz(_)) && is_label(operand) {
+        return Ok(Operand::Label(String::from(operand)));
+    }
+    Err(format!("invalid operand for {}: {}", instruction.mnemonic(), operand))
+}
+
+fn is_label(label: &str) -> bool {
+    label.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') &&
+        label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -15,14 +15,34 @@ impl Computer {
     }
 
     pub fn run_program(&mut self) {
-        while self.ip < self.program.len() {
-            let instruction = &self.program[self.ip];
-            let ip_before = self.ip;
-            Computer::execute_instruction(&mut self.registers, &mut self.ip, &mut self.output, instruction);
-            if ip_before == self.ip {
-                self.ip += 1;
-            }
+        while self.step() {}
+    }
+
+    /// Runs the instruction at the instruction pointer, returning false instead if the program
+    /// has halted.
+    pub fn step(&mut self) -> bool {
+        let Some(instruction) = self.program.get(self.ip) else {
+            return false;
+        };
+        let ip_before = self.ip;
+        Computer::execute_instruction(&mut self.registers, &mut self.ip, &mut self.output, instruction);
+        if ip_before == self.ip {
+            self.ip += 1;
         }
+        true
+    }
+
+    /// The address of the next instruction to run, counting both its opcode and operand.
+    pub fn address(&self) -> usize {
+        self.ip * 2
+    }
+
+    pub fn current_instruction(&self) -> Option<Instruction> {
+        self.program.get(self.ip).copied()
+    }
+
+    pub fn registers(&self) -> Registers {
+        self.registers
     }
 
     fn execute_instruction(
@@ -32,17 +52,27 @@ impl Computer {
         ins: &Instruction
     ) {
         match ins {
-            Instruction::Adv(opd) => regs.a = regs.a >> Self::combo(regs, *opd),
+            Instruction::Adv(opd) => regs.a = Self::divide_a(regs, *opd),
             Instruction::Bxl(opd) => regs.b = regs.b ^ opd,
             Instruction::Bst(opd) => regs.b = Self::combo(regs, *opd) % 8,
             Instruction::Jnz(opd) => if regs.a != 0 { *ip = (opd / 2) as usize },
-            Instruction::Bxc => regs.b = regs.b ^ regs.c,
+            Instruction::Bxc(_) => regs.b = regs.b ^ regs.c,
             Instruction::Out(opd) => out.push((Self::combo(regs, *opd) % 8) as u8),
-            Instruction::Bdv(opd) => regs.b = regs.a >> Self::combo(regs, *opd),
-            Instruction::Cdv(opd) => regs.c = regs.a >> Self::combo(regs, *opd),
+            Instruction::Bdv(opd) => regs.b = Self::divide_a(regs, *opd),
+            Instruction::Cdv(opd) => regs.c = Self::divide_a(regs, *opd),
         }
     }
 
+    // A divided by 2 to the power of the combo operand, which leaves nothing once that's 64 or more
+    fn divide_a(registers: &Registers, operand: u64) -> u64 {
+        u32::try_from(Self::combo(registers, operand)).ok()
+            .and_then(|shift| registers.a.checked_shr(shift))
+            .unwrap_or(0)
+    }
+
+    // combo operand 7 is reserved and won't appear in valid programs; rather than stopping on
+    // it, it stands for 7 itself like the literal operands below it (the disassembler flags it as
+    // invalid, and the assembler only takes it written as 7!)
     fn combo(registers: &Registers, operand: u64) -> u64 {
         match operand {
             4 => registers.a,
@@ -60,36 +90,77 @@ impl Computer {
     }
 }
 
-struct Registers {
-    a: u64,
-    b: u64,
-    c: u64,
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Registers {
+    pub a: u64,
+    pub b: u64,
+    pub c: u64,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Register { A, B, C }
+
+impl Registers {
+    pub fn get(&self, register: Register) -> u64 {
+        match register {
+            Register::A => self.a,
+            Register::B => self.b,
+            Register::C => self.c,
+        }
+    }
 }
 
-enum Instruction {
+#[derive(Copy, Clone)]
+pub enum Instruction {
     Adv(u64),
     Bxl(u64),
     Bst(u64),
     Jnz(u64),
-    Bxc,
+    /// Ignores its operand, which is only kept so the program can be written back out as it was.
+    Bxc(u64),
     Out(u64),
     Bdv(u64),
     Cdv(u64),
 }
 
 impl Instruction {
-    fn new(opcode: u8, operand: u64) -> Self {
+    pub fn new(opcode: u8, operand: u64) -> Self {
         match opcode {
             0 => Instruction::Adv(operand),
             1 => Instruction::Bxl(operand),
             2 => Instruction::Bst(operand),
             3 => Instruction::Jnz(operand),
-            4 => Instruction::Bxc,
+            4 => Instruction::Bxc(operand),
             5 => Instruction::Out(operand),
             6 => Instruction::Bdv(operand),
             7 => Instruction::Cdv(operand),
             _ => panic!("unknown opcode {}", opcode),
         }
     }
-}
 
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Adv(_) => "adv",
+            Instruction::Bxl(_) => "bxl",
+            Instruction::Bst(_) => "bst",
+            Instruction::Jnz(_) => "jnz",
+            Instruction::Bxc(_) => "bxc",
+            Instruction::Out(_) => "out",
+            Instruction::Bdv(_) => "bdv",
+            Instruction::Cdv(_) => "cdv",
+        }
+    }
+
+    pub fn operand(&self) -> u64 {
+        match self {
+            Instruction::Adv(opd) | Instruction::Bxl(opd) | Instruction::Bst(opd) | Instruction::Jnz(opd) |
+            Instruction::Bxc(opd) | Instruction::Out(opd) | Instruction::Bdv(opd) | Instruction::Cdv(opd) => *opd,
+        }
+    }
+
+    /// Whether the operand is a combo operand, where 4 to 6 stand for registers A to C, rather
+    /// than a literal.
+    pub fn takes_combo(&self) -> bool {
+        matches!(self, Instruction::Adv(_) | Instruction::Bst(_) | Instruction::Out(_) | Instruction::Bdv(_) | Instruction::Cdv(_))
+    }
+}
@@ -0,0 +1,139 @@
+use crate::day_17::computer::{Computer, Instruction, Register, Registers};
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Breakpoint {
+    /// Stops before the instruction at this address runs.
+    Address(usize),
+    /// Stops after anything is output.
+    Output,
+    /// Stops after this value is output.
+    OutputValue(u8),
+}
+
+/// Why a run stopped.
+#[derive(Eq, PartialEq, Debug)]
+pub enum Stop {
+    Halted,
+    Breakpoint(Breakpoint),
+    /// A watched register changed.
+    Watch { register: Register, before: u64, after: u64 },
+    /// The limit on how many instructions can be run was reached.
+    Limit,
+    /// The computer is back in the same state it was in after `since` instructions, so it will
+    /// go round the same instructions forever without halting.
+    Loop { since: usize },
+}
+
+/// One instruction that was run.
+pub struct TraceEntry {
+    pub address: usize,
+    pub instruction: Instruction,
+    pub before: Registers,
+    pub after: Registers,
+    pub output: Option<u8>,
+}
+
+/// Runs a computer's program an instruction at a time, keeping a trace of every instruction
+/// run, and stopping at breakpoints, when watched registers change, or when the program seems
+/// not to halt.
+pub struct Debugger {
+    computer: Computer,
+    breakpoints: Vec<Breakpoint>,
+    watches: Vec<Register>,
+    limit: Option<usize>,
+    trace: Vec<TraceEntry>,
+    /// How many instructions had been run the first time the computer was in each state (its
+    /// address and registers) during the current run. Only runs record states, and each run
+    /// starts afresh, so a loop is only reported once the run itself has gone round it.
+    seen: HashMap<(usize, Registers), usize>,
+}
+
+impl Debugger {
+    pub fn new(computer: Computer) -> Self {
+        Debugger {
+            computer,
+            breakpoints: Vec::new(),
+            watches: Vec::new(),
+            limit: None,
+            trace: Vec::new(),
+            seen: HashMap::new(),
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+
+    pub fn watch(&mut self, register: Register) {
+        self.watches.push(register);
+    }
+
+    /// Stops runs once `limit` instructions have been run in all.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = Some(limit);
+    }
+
+    pub fn computer(&self) -> &Computer {
+        &self.computer
+    }
+
+    pub fn trace(&self) -> &[TraceEntry] {
+        &self.trace
+    }
+
+    /// Runs the next instruction, whatever the breakpoints, watches and limit, unless the
+    /// program has halted.
+    pub fn step(&mut self) -> Option<&TraceEntry> {
+        let instruction = self.computer.current_instruction()?;
+        let address = self.computer.address();
+        let before = self.computer.registers();
+        let output_len = self.computer.output.len();
+        self.computer.step();
+        let output = self.computer.output.get(output_len).copied();
+        let after = self.computer.registers();
+        self.trace.push(TraceEntry { address, instruction, before, after, output });
+        self.trace.last()
+    }
+
+    /// Runs instructions until the program halts or something makes it stop. An address
+    /// breakpoint stops the run before its instruction, apart from the first instruction run,
+    /// so that a run can carry on from a breakpoint. Output breakpoints and watches stop the
+    /// run after the instruction that set them off.
+    pub fn run(&mut self) -> Stop {
+        self.seen.clear();
+        let mut first = true;
+        loop {
+            if self.computer.current_instruction().is_none() {
+                return Stop::Halted;
+            }
+            let address = self.computer.address();
+            if !first && self.breakpoints.contains(&Breakpoint::Address(address)) {
+                return Stop::Breakpoint(Breakpoint::Address(address));
+            }
+            first = false;
+            if self.limit.is_some_and(|limit| self.trace.len() >= limit) {
+                return Stop::Limit;
+            }
+            self.seen.entry((address, self.computer.registers())).or_insert(self.trace.len());
+
+            self.step();
+            let entry = self.trace.last().unwrap();
+            if let Some(value) = entry.output {
+                let breakpoint = self.breakpoints.iter()
+                    .find(|&&breakpoint| breakpoint == Breakpoint::Output || breakpoint == Breakpoint::OutputValue(value));
+                if let Some(&breakpoint) = breakpoint {
+                    return Stop::Breakpoint(breakpoint);
+                }
+            }
+            let changed = self.watches.iter()
+                .find(|&&register| entry.before.get(register) != entry.after.get(register));
+            if let Some(&register) = changed {
+                return Stop::Watch { register, before: entry.before.get(register), after: entry.after.get(register) };
+            }
+            if let Some(&since) = self.seen.get(&(self.computer.address(), self.computer.registers())) {
+                return Stop::Loop { since };
+            }
+        }
+    }
+}
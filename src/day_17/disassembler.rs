@@ -0,0 +1,182 @@
+use crate::day_17::computer::Instruction;
+
+/// One instruction of a program, and the address of its opcode.
+pub struct Line {
+    pub address: usize,
+    pub instruction: Instruction,
+}
+
+/// The program's instructions, leaving out any opcode at the end without an operand.
+pub fn disassemble(program: &[u8]) -> Vec<Line> {
+    program.chunks_exact(2)
+        .enumerate()
+        .map(|(index, chunk)| Line { address: index * 2, instruction: Instruction::new(chunk[0], u64::from(chunk[1])) })
+        .collect()
+}
+
+/// The program with one instruction to a line, each after its address, such as `4  bst A`.
+/// Instructions using combo operand 7, which doesn't stand for anything, are flagged, as is an
+/// opcode left over at the end without an operand.
+pub fn listing(program: &[u8]) -> String {
+    let lines = disassemble(program);
+    let width = program.len().saturating_sub(1).to_string().len();
+    let mut s: String = lines.iter()
+        .map(|line| {
+            let mut text = format!("{:>width$}  {}", line.address, instruction_text(&line.instruction));
+            if let Some(problem) = problem(&line.instruction) {
+                text.push_str("  ; ");
+                text.push_str(&problem);
+            }
+            text.push('\n');
+            text
+        })
+        .collect();
+    if let Some(opcode) = trailing_opcode(program) {
+        s.push_str(&format!("{:>width$}  {}  ; {}\n", program.len() - 1, mnemonic(opcode), TRAILING_PROBLEM));
+    }
+    s
+}
+
+/// The program as source for the assembler, with a label on every instruction that's jumped
+/// to: `loop` for where the jump at the end goes back to, and otherwise `at_` and its address.
+/// Anything flagged in the listing is written so that it still assembles: combo operand 7 as
+/// `7!`, and an opcode left over at the end with `-` for its operand.
+pub fn source(program: &[u8]) -> String {
+    let lines = disassemble(program);
+    let label = |address: u64| -> Option<String> {
+        let is_jumped_to = address.is_multiple_of(2) && address < lines.len() as u64 * 2 && lines.iter()
+            .any(|line| matches!(line.instruction, Instruction::Jnz(target) if target == address));
+        if !is_jumped_to {
+            None
+        } else if matches!(lines.last(), Some(Line { instruction: Instruction::Jnz(target), .. }) if *target == address) {
+            Some(String::from("loop"))
+        } else {
+            Some(format!("at_{}", address))
+        }
+    };
+    let mut s = String::new();
+    for line in &lines {
+        if let Some(label) = label(line.address as u64) {
+            s.push_str(&format!("{}:\n", label));
+        }
+        let text = match line.instruction {
+            Instruction::Jnz(target) if let Some(label) = label(target) => format!("jnz {}", label),
+            _ if problem(&line.instruction).is_some() => {
+                format!("{} {}!", line.instruction.mnemonic(), line.instruction.operand())
+            }
+            _ => instruction_text(&line.instruction),
+        };
+        s.push_str(&format!("    {}", text));
+        if let Some(problem) = problem(&line.instruction) {
+            s.push_str("  ; ");
+            s.push_str(&problem);
+        }
+        s.push('\n');
+    }
+    if let Some(opcode) = trailing_opcode(program) {
+        s.push_str(&format!("    {} -  ; {}\n", mnemonic(opcode), TRAILING_PROBLEM));
+    }
+    s
+}
+
+/// The instruction as mnemonic text, such as `adv 3` or `bst A`. `bxc` ignores its operand, so
+/// it's left off unless it isn't 0.
+pub fn instruction_text(instruction: &Instruction) -> String {
+    match instruction {
+        Instruction::Bxc(0) => String::from("bxc"),
+        _ => format!("{} {}", instruction.mnemonic(), operand_text(instruction)),
+    }
+}
+
+const TRAILING_PROBLEM: &str = "no operand, so the program halts here";
+
+/// The opcode at the end of a program with an odd length, which can't be run as there's no
+/// operand after it.
+fn trailing_opcode(program: &[u8]) -> Option<u8> {
+    (program.len() % 2 == 1).then(|| program[program.len() - 1])
+}
+
+fn mnemonic(opcode: u8) -> &'static str {
+    Instruction::new(opcode, 0).mnemonic()
+}
+
+/// What's wrong with the instruction, if anything.
+pub fn problem(instruction: &Instruction) -> Option<String> {
+    (instruction.takes_combo() && instruction.operand() > 6)
+        .then(|| format!("invalid combo operand {}", instruction.operand()))
+}
+
+fn operand_text(instruction: &Instruction) -> String {
+    match (instruction.takes_combo(), instruction.operand()) {
+        (true, 4) => String::from("A"),
+        (true, 5) => String::from("B"),
+        (true, 6) => String::from("C"),
+        (_, operand) => operand.to_string(),
+    }
+}
+
+/// What the program does, written as one statement per instruction. When the program ends by
+/// jumping back, as programs that output more than once usually do, the instructions from the
+/// jump target on are shown as the body of a loop.
+pub fn pseudo_code(program: &[u8]) -> String {
+    let lines = disassemble(program);
+    let loop_start = match lines.last() {
+        Some(Line { address, instruction: Instruction::Jnz(target) }) if jump_address(*target) < *address as u64 => {
+            Some(lines.iter().position(|line| line.address as u64 == jump_address(*target)).unwrap())
+        }
+        _ => None,
+    };
+    let mut s = String::new();
+    match loop_start {
+        Some(loop_start) => {
+            for line in &lines[..loop_start] {
+                s.push_str(&format!("{}\n", statement(&line.instruction)));
+            }
+            s.push_str("do {\n");
+            for line in &lines[loop_start..lines.len() - 1] {
+                s.push_str(&format!("    {}\n", statement(&line.instruction)));
+            }
+            s.push_str("} while A != 0\n");
+        }
+        None => {
+            for line in &lines {
+                s.push_str(&format!("{}\n", statement(&line.instruction)));
+            }
+        }
+    }
+    s
+}
+
+fn statement(instruction: &Instruction) -> String {
+    match instruction {
+        Instruction::Adv(_) => format!("A = A >> {}", combo_value(instruction)),
+        Instruction::Bxl(operand) => format!("B = B ^ {}", operand),
+        Instruction::Bst(_) => format!("B = {}", combo_mod_8(instruction)),
+        Instruction::Jnz(target) => format!("if A != 0 goto {}", jump_address(*target)),
+        Instruction::Bxc(_) => String::from("B = B ^ C"),
+        Instruction::Out(_) => format!("output {}", combo_mod_8(instruction)),
+        Instruction::Bdv(_) => format!("B = A >> {}", combo_value(instruction)),
+        Instruction::Cdv(_) => format!("C = A >> {}", combo_value(instruction)),
+    }
+}
+
+/// The address of the instruction that a jump to `target` goes to. The computer jumps to
+/// instructions rather than addresses, so an odd target goes to the opcode just before it.
+fn jump_address(target: u64) -> u64 {
+    target / 2 * 2
+}
+
+fn combo_value(instruction: &Instruction) -> String {
+    match problem(instruction) {
+        Some(_) => format!("<invalid {}>", instruction.operand()),
+        None => operand_text(instruction),
+    }
+}
+
+/// The combo operand's value modulo 8, which a literal already is.
+fn combo_mod_8(instruction: &Instruction) -> String {
+    match instruction.operand() {
+        4..=6 => format!("{} % 8", operand_text(instruction)),
+        _ => combo_value(instruction),
+    }
+}
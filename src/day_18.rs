@@ -7,6 +7,7 @@ pub const DAY_EIGHTEEN: DaySpec<u32, String> = DaySpec {
     part_1,
     part_2_name: "first byte preventing escape",
     part_2,
+    exports: &[],
 };
 
 fn part_1(input: &str) -> u32 {
@@ -7,6 +7,7 @@ pub const DAY_NINETEEN: DaySpec<u32, u64> = DaySpec {
     part_1,
     part_2_name: "possible ways to make all designs",
     part_2,
+    exports: &[],
 };
 
 fn part_1(input: &str) -> u32 {
@@ -0,0 +1,66 @@
+use std::fmt;
+use std::fmt::Display;
+
+pub struct DotGraph {
+    name: String,
+    nodes: Vec<DotElement>,
+    edges: Vec<(String, String, DotElement)>,
+}
+
+struct DotElement {
+    id: String,
+    attributes: Vec<(&'static str, String)>,
+}
+
+impl DotGraph {
+    pub fn new(name: &str) -> Self {
+        DotGraph { name: name.to_string(), nodes: Vec::new(), edges: Vec::new() }
+    }
+
+    pub fn add_node(&mut self, id: &str, attributes: &[(&'static str, &str)]) {
+        self.nodes.push(DotElement::new(id, attributes));
+    }
+
+    pub fn add_edge(&mut self, from: &str, to: &str, attributes: &[(&'static str, &str)]) {
+        self.edges.push((from.to_string(), to.to_string(), DotElement::new("", attributes)));
+    }
+}
+
+impl DotElement {
+    fn new(id: &str, attributes: &[(&'static str, &str)]) -> Self {
+        DotElement {
+            id: id.to_string(),
+            attributes: attributes.iter()
+                .map(|&(key, value)| (key, value.to_string()))
+                .collect(),
+        }
+    }
+
+    fn attributes_str(&self) -> String {
+        if self.attributes.is_empty() {
+            return String::new();
+        }
+        let attributes = self.attributes.iter()
+            .map(|(key, value)| format!("{}={}", key, quote(value)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(" [{}]", attributes)
+    }
+}
+
+impl Display for DotGraph {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "digraph {} {{", quote(&self.name))?;
+        for node in &self.nodes {
+            writeln!(f, "    {}{};", quote(&node.id), node.attributes_str())?;
+        }
+        for (from, to, edge) in &self.edges {
+            writeln!(f, "    {} -> {}{};", quote(from), quote(to), edge.attributes_str())?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,171 @@
+/// A greyscale image with one byte per pixel, 0 being black and 255 white.
+pub struct GreyImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+#[derive(Copy, Clone)]
+pub enum ImageFormat {
+    /// Binary PBM, where every pixel that isn't black comes out black on a white background, as
+    /// is usual for bitmaps.
+    Pbm,
+    Pgm,
+    Png,
+}
+
+impl GreyImage {
+    pub fn new(width: usize, height: usize, level: u8) -> Self {
+        GreyImage { width, height, pixels: vec![level; width * height] }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, level: u8) {
+        self.pixels[y * self.width + x] = level;
+    }
+
+    /// Every pixel made into a `factor` by `factor` square.
+    pub fn scaled(&self, factor: usize) -> GreyImage {
+        let mut scaled = GreyImage::new(self.width * factor, self.height * factor, 0);
+        for y in 0..scaled.height {
+            for x in 0..scaled.width {
+                scaled.set(x, y, self.pixels[y / factor * self.width + x / factor]);
+            }
+        }
+        scaled
+    }
+
+    /// Copies `other` into this image with its top left corner at `x`, `y`.
+    pub fn draw(&mut self, other: &GreyImage, x: usize, y: usize) {
+        for row in 0..other.height {
+            let start = (y + row) * self.width + x;
+            self.pixels[start..start + other.width]
+                .copy_from_slice(&other.pixels[row * other.width..(row + 1) * other.width]);
+        }
+    }
+
+    pub fn encode(&self, format: ImageFormat) -> Vec<u8> {
+        match format {
+            ImageFormat::Pbm => self.pbm(),
+            ImageFormat::Pgm => self.pgm(),
+            ImageFormat::Png => self.png(),
+        }
+    }
+
+    fn pbm(&self) -> Vec<u8> {
+        let mut bytes = format!("P4\n{} {}\n", self.width, self.height).into_bytes();
+        for row in self.pixels.chunks(self.width.max(1)) {
+            // eight pixels to a byte, first pixel in the highest bit, with each row padded out
+            // to a whole byte
+            for pixels in row.chunks(8) {
+                let byte = pixels.iter()
+                    .enumerate()
+                    .filter(|&(_, &level)| level > 0)
+                    .fold(0, |byte, (bit, _)| byte | (0x80 >> bit));
+                bytes.push(byte);
+            }
+        }
+        bytes
+    }
+
+    fn pgm(&self) -> Vec<u8> {
+        let mut bytes = format!("P5\n{} {}\n255\n", self.width, self.height).into_bytes();
+        bytes.extend_from_slice(&self.pixels);
+        bytes
+    }
+
+    /// An 8-bit greyscale PNG, without any actual compression: the zlib stream is made of
+    /// stored deflate blocks.
+    fn png(&self) -> Vec<u8> {
+        let mut bytes = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&u32::try_from(self.width).unwrap().to_be_bytes());
+        header.extend_from_slice(&u32::try_from(self.height).unwrap().to_be_bytes());
+        // bit depth 8, greyscale, deflate, adaptive filtering, no interlacing
+        header.extend_from_slice(&[8, 0, 0, 0, 0]);
+        write_chunk(&mut bytes, b"IHDR", &header);
+
+        // each row starts with the filter type used for it, which is always none
+        let mut scanlines = Vec::with_capacity((self.width + 1) * self.height);
+        for row in self.pixels.chunks(self.width.max(1)) {
+            scanlines.push(0);
+            scanlines.extend_from_slice(row);
+        }
+        write_chunk(&mut bytes, b"IDAT", &zlib_stored(&scanlines));
+
+        write_chunk(&mut bytes, b"IEND", &[]);
+        bytes
+    }
+}
+
+impl ImageFormat {
+    pub fn parse(format: &str) -> Self {
+        match format {
+            "pbm" => ImageFormat::Pbm,
+            "pgm" => ImageFormat::Pgm,
+            "png" => ImageFormat::Png,
+            _ => panic!("unknown image format: {}", format),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Pbm => "pbm",
+            ImageFormat::Pgm => "pgm",
+            ImageFormat::Png => "png",
+        }
+    }
+}
+
+fn write_chunk(bytes: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    bytes.extend_from_slice(&u32::try_from(data.len()).unwrap().to_be_bytes());
+    let start = bytes.len();
+    bytes.extend_from_slice(chunk_type);
+    bytes.extend_from_slice(data);
+    // the checksum covers the type and data but not the length
+    let crc = crc32(&bytes[start..]);
+    bytes.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// `data` wrapped up as a zlib stream of stored (uncompressed) deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK: usize = 0xffff;
+    // deflate with a 32K window, no preset dictionary, and a check value making the header a
+    // multiple of 31
+    let mut bytes = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        bytes.extend_from_slice(&[0x01, 0x00, 0x00, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let len = u16::try_from(block.len()).unwrap();
+        bytes.push(u8::from(is_final));
+        bytes.extend_from_slice(&len.to_le_bytes());
+        bytes.extend_from_slice(&(!len).to_le_bytes());
+        bytes.extend_from_slice(block);
+    }
+    bytes.extend_from_slice(&adler32(data).to_be_bytes());
+    bytes
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MODULUS: u32 = 65521;
+    let (mut a, mut b) = (1, 0);
+    for &byte in data {
+        a = (a + u32::from(byte)) % MODULUS;
+        b = (b + a) % MODULUS;
+    }
+    (b << 16) | a
+}
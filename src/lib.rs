@@ -1,4 +1,9 @@
 mod common;
+mod dot;
+mod image;
+mod svg;
+#[cfg(test)]
+mod test_support;
 mod day_01;
 mod day_02;
 mod day_03;
@@ -1,16 +1,47 @@
 use advent_of_code_2024_rust::days;
 use advent_of_code_2024_rust::PartOutput;
 use std::borrow::Cow;
+use std::env;
+use std::process;
 
 fn main() {
-    let days = days();
-    for day in days {
-        println!("Day {}", day.day_num());
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut days = days();
+    let Some((day_arg, export_args)) = args.split_first() else {
+        for day in days {
+            println!("Day {}", day.day_num());
+            let input = day.read_input().unwrap();
+            println!("Part 1 ({}): {}", day.part_1_name(), part_output_str(day.run_part_1(&input)));
+            println!("Part 2 ({}): {}", day.part_2_name(), part_output_str(day.run_part_2(&input)));
+            println!();
+        }
+        return;
+    };
+    days.retain(|day| day_arg.parse() == Ok(day.day_num()));
+    let Some(day) = days.first() else {
+        exit_with_usage(&format!("{} is not a valid day", day_arg));
+    };
+    let Some((export_name, export_args)) = export_args.split_first() else {
         let input = day.read_input().unwrap();
         println!("Part 1 ({}): {}", day.part_1_name(), part_output_str(day.run_part_1(&input)));
         println!("Part 2 ({}): {}", day.part_2_name(), part_output_str(day.run_part_2(&input)));
-        println!();
-    }
+        return;
+    };
+    let Some(export) = day.exports().iter().find(|export| export.name == export_name) else {
+        let mut message = format!("day {} has no export named {}", day.day_num(), export_name);
+        for export in day.exports() {
+            message.push_str(&format!("\n  {}: {}", export.name, export.description));
+        }
+        exit_with_usage(&message);
+    };
+    let input = if export.needs_input { day.read_input().unwrap() } else { Cow::Borrowed("") };
+    print!("{}", (export.run)(&input, export_args));
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("usage: advent-of-code-2024-rust [<day> [<export> [<args>...]]]");
+    process::exit(1);
 }
 
 fn part_output_str(output: PartOutput) -> Cow<'static, str> {
@@ -0,0 +1,88 @@
+use std::fmt;
+use std::fmt::Display;
+
+pub struct SvgDocument {
+    width: usize,
+    height: usize,
+    elements: Vec<SvgElement>,
+}
+
+/// An element with its attributes, an optional `<title>` (shown as a tooltip when hovering over
+/// it) and any child elements.
+pub struct SvgElement {
+    name: &'static str,
+    attributes: Vec<(&'static str, String)>,
+    title: Option<String>,
+    children: Vec<SvgElement>,
+}
+
+impl SvgDocument {
+    pub fn new(width: usize, height: usize) -> Self {
+        SvgDocument { width, height, elements: Vec::new() }
+    }
+
+    pub fn add_element(&mut self, element: SvgElement) {
+        self.elements.push(element);
+    }
+}
+
+impl SvgElement {
+    pub fn new(name: &'static str, attributes: &[(&'static str, &str)]) -> Self {
+        SvgElement {
+            name,
+            attributes: attributes.iter()
+                .map(|&(key, value)| (key, value.to_string()))
+                .collect(),
+            title: None,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.title = Some(title.to_string());
+    }
+
+    pub fn add_child(&mut self, child: SvgElement) {
+        self.children.push(child);
+    }
+
+    fn write(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        write!(f, "{}<{}", "  ".repeat(indent), self.name)?;
+        for (key, value) in &self.attributes {
+            write!(f, " {}=\"{}\"", key, escape(value))?;
+        }
+        if self.title.is_none() && self.children.is_empty() {
+            return writeln!(f, "/>");
+        }
+        writeln!(f, ">")?;
+        if let Some(title) = &self.title {
+            writeln!(f, "{}<title>{}</title>", "  ".repeat(indent + 1), escape(title))?;
+        }
+        for child in &self.children {
+            child.write(f, indent + 1)?;
+        }
+        writeln!(f, "{}</{}>", "  ".repeat(indent), self.name)
+    }
+}
+
+impl Display for SvgDocument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">",
+            self.width,
+            self.height,
+        )?;
+        for element in &self.elements {
+            element.write(f, 1)?;
+        }
+        writeln!(f, "</svg>")
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
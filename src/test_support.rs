@@ -0,0 +1,17 @@
+/// A seeded linear congruential generator, for tests that generate their inputs, so that the
+/// inputs are the same every run.
+pub struct Lcg {
+    seed: u64,
+}
+
+impl Lcg {
+    pub fn new(seed: u64) -> Self {
+        Lcg { seed }
+    }
+
+    /// A number from 0 up to but not including `range`.
+    pub fn below(&mut self, range: u64) -> u64 {
+        self.seed = (self.seed * 1103515245 + 12345) % (1 << 31);
+        (self.seed >> 16) % range
+    }
+}